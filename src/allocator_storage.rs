@@ -0,0 +1,118 @@
+use crate::{
+    MultipleStorage, PointerStorage, ShareableStorage, StableStorage, Storage, StorageAllocError,
+    StorageHandle,
+};
+use core::{
+    alloc::{Allocator, Layout},
+    ptr::NonNull,
+};
+
+/// The [`StorageHandle`] for [`AllocatorStorage`],
+/// this is a wrapper around a [`NonNull<()>`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AllocatorStorageHandle(pub NonNull<()>);
+
+unsafe impl Send for AllocatorStorageHandle {}
+unsafe impl Sync for AllocatorStorageHandle {}
+
+impl StorageHandle for AllocatorStorageHandle {}
+
+/// A [`Storage`] that allocates from an [`Allocator`]
+///
+/// ```
+/// #![feature(allocator_api)]
+///
+/// use std::alloc::System;
+/// use storage_api::{AllocatorStorage, Vec};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let mut v = Vec::<i32, _>::new_in(AllocatorStorage::new(System))?;
+/// v.extend_from_slice(&[1, 2, 3])?;
+/// assert_eq!(&*v, &[1, 2, 3]);
+///
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default, Clone, Copy)]
+pub struct AllocatorStorage<A> {
+    allocator: A,
+}
+
+impl<A> AllocatorStorage<A> {
+    /// Constructs a new [`AllocatorStorage`] that allocates from `allocator`
+    pub const fn new(allocator: A) -> Self {
+        Self { allocator }
+    }
+
+    /// Returns a reference to the [`Allocator`]
+    pub fn allocator(&self) -> &A {
+        &self.allocator
+    }
+
+    /// Returns the [`Allocator`]
+    pub fn into_inner(self) -> A {
+        self.allocator
+    }
+}
+
+unsafe impl<A: Allocator> Storage for AllocatorStorage<A> {
+    type Handle = AllocatorStorageHandle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        handle.0
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let ptr = self
+            .allocator
+            .allocate(layout)
+            .map_err(|_| StorageAllocError)?;
+        Ok((AllocatorStorageHandle(ptr.cast()), ptr.len()))
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        unsafe { self.allocator.deallocate(handle.0.cast(), layout) }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let ptr = unsafe { self.allocator.grow(handle.0.cast(), old_layout, new_layout) }
+            .map_err(|_| StorageAllocError)?;
+        Ok((AllocatorStorageHandle(ptr.cast()), ptr.len()))
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let ptr = unsafe {
+            self.allocator
+                .shrink(handle.0.cast(), old_layout, new_layout)
+        }
+        .map_err(|_| StorageAllocError)?;
+        Ok((AllocatorStorageHandle(ptr.cast()), ptr.len()))
+    }
+}
+
+/// Clones of an [`Allocator`] are required to act as the same allocator
+unsafe impl<A: Allocator + Clone> ShareableStorage for AllocatorStorage<A> {
+    unsafe fn make_shared_copy(&self) -> Self {
+        self.clone()
+    }
+}
+
+unsafe impl<A: Allocator> MultipleStorage for AllocatorStorage<A> {}
+unsafe impl<A: Allocator> StableStorage for AllocatorStorage<A> {}
+unsafe impl<A: Allocator> PointerStorage for AllocatorStorage<A> {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        AllocatorStorageHandle(ptr)
+    }
+}
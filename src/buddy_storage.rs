@@ -0,0 +1,286 @@
+use crate::{
+    MultipleStorage, PointerStorage, StableStorage, Storage, StorageAllocError, StorageHandle,
+};
+use core::{alloc::Layout, cell::Cell, marker::PhantomData, mem::MaybeUninit, ptr::NonNull};
+
+/// The [`StorageHandle`] for [`BuddyStorage`],
+/// this is the offset of the allocation from the start of the region
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BuddyStorageHandle {
+    offset: usize,
+}
+
+impl StorageHandle for BuddyStorageHandle {}
+
+/// Stored at the start of every free block, each order has its own doubly linked list of free blocks
+#[derive(Clone, Copy)]
+struct FreeNode {
+    previous: usize,
+    next: usize,
+}
+
+const NONE: usize = usize::MAX;
+const MIN_ORDER: u32 = size_of::<FreeNode>().trailing_zeros();
+const ORDERS: usize = usize::BITS as usize;
+
+/// Returns how many bytes are needed to store one bit for every block in a region of size `1 << order`
+const fn bitmap_size(order: u32) -> usize {
+    let blocks = 1usize << (order - MIN_ORDER + 1);
+    blocks.div_ceil(8)
+}
+
+/// A buddy allocator, that splits a power of two sized region into power of two sized blocks
+///
+/// Allocating and deallocating both take at most one step for each power of two between the smallest block and the whole region,
+/// and the size of the block is returned from [`Storage::allocate`]/[`Storage::grow`]/[`Storage::shrink`] so collections can use all of it
+///
+/// Some of the buffer is used to keep track of which blocks are free, so the region is the largest power of two that fits in the rest of it
+///
+/// ```
+/// use core::mem::MaybeUninit;
+/// use storage_api::{BuddyStorage, Vec};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let mut buffer = [MaybeUninit::uninit(); 1100];
+/// let storage = BuddyStorage::from_uninit(&mut buffer);
+/// assert_eq!(storage.capacity(), 1024);
+///
+/// let mut v = Vec::<u32, _>::with_capacity_in(5, &storage)?;
+/// assert_eq!(v.capacity(), 8); // 20 bytes was rounded up to a 32 byte block
+/// v.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9])?;
+/// assert_eq!(v.capacity(), 16);
+///
+/// assert!(Vec::<u8, _>::with_capacity_in(1024, &storage).is_err());
+/// drop(v);
+/// assert!(Vec::<u8, _>::with_capacity_in(1024, &storage).is_ok()); // the blocks were merged back together
+///
+/// # Ok(())
+/// # }
+/// ```
+pub struct BuddyStorage<'a> {
+    base: NonNull<u8>,
+    bitmap: NonNull<u8>,
+    order: u32,
+    capacity: usize,
+    max_align: usize,
+    free_lists: [Cell<usize>; ORDERS],
+    _buffer: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+unsafe impl Send for BuddyStorage<'_> {}
+
+impl<'a> BuddyStorage<'a> {
+    /// Constructs a [`BuddyStorage`] that allocates from an uninitialised `buffer`
+    pub fn from_uninit(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        let start = NonNull::from(&mut *buffer).cast::<u8>();
+        let padding = start.align_offset(1 << MIN_ORDER).min(buffer.len());
+        let length = buffer.len() - padding;
+        let base = unsafe { start.add(padding) };
+
+        let order = (MIN_ORDER..usize::BITS).rev().find(|&order| {
+            (1usize << order)
+                .checked_add(bitmap_size(order))
+                .is_some_and(|size| size <= length)
+        });
+
+        let mut this = Self {
+            base,
+            bitmap: base,
+            order: MIN_ORDER,
+            capacity: 0,
+            max_align: 1,
+            free_lists: [const { Cell::new(NONE) }; ORDERS],
+            _buffer: PhantomData,
+        };
+        if let Some(order) = order {
+            this.order = order;
+            this.capacity = 1 << order;
+            this.max_align = 1 << base.addr().get().trailing_zeros().min(order);
+            unsafe {
+                this.bitmap = base.add(1 << order);
+                this.bitmap.write_bytes(0, bitmap_size(order));
+                this.push(0, order);
+            }
+        }
+        this
+    }
+
+    /// Returns the size of the region that blocks are allocated from, this is always a power of two
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the order of the block that an allocation with `layout` needs
+    fn order_of(&self, layout: Layout) -> Option<u32> {
+        if layout.align() > self.max_align {
+            return None;
+        }
+        let size = layout
+            .size()
+            .max(layout.align())
+            .max(1 << MIN_ORDER)
+            .checked_next_power_of_two()?;
+        Some(size.trailing_zeros()).filter(|&order| order <= self.order)
+    }
+
+    fn bit(&self, offset: usize, order: u32) -> (NonNull<u8>, u8) {
+        let index = (1usize << (self.order - order)) + (offset >> order);
+        (unsafe { self.bitmap.add(index / 8) }, 1 << (index % 8))
+    }
+
+    /// Returns `true` if the block at `offset` with the size `1 << order` is free
+    fn is_free(&self, offset: usize, order: u32) -> bool {
+        let (byte, mask) = self.bit(offset, order);
+        unsafe { byte.read() & mask != 0 }
+    }
+
+    unsafe fn node(&self, offset: usize) -> NonNull<FreeNode> {
+        unsafe { self.base.add(offset).cast() }
+    }
+
+    /// Adds the block at `offset` to the free list for `order`
+    unsafe fn push(&self, offset: usize, order: u32) {
+        let head = self.free_lists[order as usize].replace(offset);
+        unsafe {
+            self.node(offset).write(FreeNode {
+                previous: NONE,
+                next: head,
+            });
+            if head != NONE {
+                (*self.node(head).as_ptr()).previous = offset;
+            }
+
+            let (byte, mask) = self.bit(offset, order);
+            byte.write(byte.read() | mask);
+        }
+    }
+
+    /// Removes the block at `offset` from the free list for `order`
+    unsafe fn remove(&self, offset: usize, order: u32) {
+        unsafe {
+            let FreeNode { previous, next } = self.node(offset).read();
+            if previous == NONE {
+                self.free_lists[order as usize].set(next);
+            } else {
+                (*self.node(previous).as_ptr()).next = next;
+            }
+            if next != NONE {
+                (*self.node(next).as_ptr()).previous = previous;
+            }
+
+            let (byte, mask) = self.bit(offset, order);
+            byte.write(byte.read() & !mask);
+        }
+    }
+
+    /// Frees the block at `offset`, merging it with its buddy for as long as the buddy is free
+    unsafe fn free(&self, mut offset: usize, mut order: u32) {
+        while order < self.order {
+            let buddy = offset ^ (1 << order);
+            if !self.is_free(buddy, order) {
+                break;
+            }
+            unsafe { self.remove(buddy, order) };
+            offset &= !(1 << order);
+            order += 1;
+        }
+        unsafe { self.push(offset, order) };
+    }
+
+    /// Splits the block at `offset` from `order` down to `new_order`, freeing the upper halves
+    unsafe fn split(&self, offset: usize, order: u32, new_order: u32) {
+        for order in (new_order..order).rev() {
+            unsafe { self.push(offset + (1 << order), order) };
+        }
+    }
+}
+
+unsafe impl Storage for BuddyStorage<'_> {
+    type Handle = BuddyStorageHandle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { self.base.add(handle.offset).cast() }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let order = self.order_of(layout).ok_or(StorageAllocError)?;
+        let (offset, found_order) = (order..=self.order)
+            .find_map(|order| {
+                let offset = self.free_lists[order as usize].get();
+                (offset != NONE).then_some((offset, order))
+            })
+            .ok_or(StorageAllocError)?;
+
+        unsafe {
+            self.remove(offset, found_order);
+            self.split(offset, found_order, order);
+        }
+        Ok((BuddyStorageHandle { offset }, 1 << order))
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        unsafe {
+            let order = self.order_of(layout).unwrap_unchecked();
+            self.free(handle.offset, order);
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let old_order = unsafe { self.order_of(old_layout).unwrap_unchecked() };
+        let new_order = self.order_of(new_layout).ok_or(StorageAllocError)?;
+        if new_order <= old_order {
+            return Ok((handle, 1 << old_order));
+        }
+
+        let offset = handle.offset;
+        if offset.trailing_zeros() >= new_order
+            && (old_order..new_order).all(|order| self.is_free(offset + (1 << order), order))
+        {
+            for order in old_order..new_order {
+                unsafe { self.remove(offset + (1 << order), order) };
+            }
+            return Ok((handle, 1 << new_order));
+        }
+
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            self.resolve(new_handle)
+                .cast::<u8>()
+                .copy_from_nonoverlapping(self.resolve(handle).cast(), old_layout.size());
+            self.free(offset, old_order);
+        }
+        Ok((new_handle, new_size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let old_order = unsafe { self.order_of(old_layout).unwrap_unchecked() };
+        let new_order = self
+            .order_of(new_layout)
+            .ok_or(StorageAllocError)?
+            .min(old_order);
+        unsafe { self.split(handle.offset, old_order, new_order) };
+        Ok((handle, 1 << new_order))
+    }
+}
+
+unsafe impl MultipleStorage for BuddyStorage<'_> {}
+unsafe impl StableStorage for BuddyStorage<'_> {}
+unsafe impl PointerStorage for BuddyStorage<'_> {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        BuddyStorageHandle {
+            offset: ptr.addr().get() - self.base.addr().get(),
+        }
+    }
+}
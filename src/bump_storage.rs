@@ -0,0 +1,280 @@
+use crate::{
+    Global, MultipleStorage, PointerStorage, StableStorage, Storage, StorageAllocError,
+    StorageHandle, global_storage::GlobalHandle,
+};
+use core::{alloc::Layout, cell::Cell, marker::PhantomData, mem::MaybeUninit, ptr::NonNull};
+
+/// The [`StorageHandle`] for [`BumpStorage`],
+/// this is a wrapper around a pointer to the allocation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BumpStorageHandle(NonNull<()>);
+
+unsafe impl Send for BumpStorageHandle {}
+unsafe impl Sync for BumpStorageHandle {}
+
+impl StorageHandle for BumpStorageHandle {}
+
+/// The header at the start of every chunk that a [`BumpStorage`] allocates from [`Global`]
+struct ChunkHeader {
+    previous: Option<NonNull<ChunkHeader>>,
+    layout: Layout,
+}
+
+/// The smallest amount of bytes a chained chunk will be allocated with
+const MIN_CHUNK_SIZE: usize = 4096;
+
+/// An arena that allocates by bumping a pointer through a buffer
+///
+/// Deallocating does nothing unless the allocation was the last one made, all allocations are freed at once with [`BumpStorage::reset`]
+///
+/// When chaining is enabled (see [`BumpStorage::set_chaining`]), a new chunk is allocated from [`Global`] once the current one fills up
+///
+/// ```
+/// use core::mem::MaybeUninit;
+/// use storage_api::{BumpStorage, Box, Vec, String};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let mut buffer = [MaybeUninit::uninit(); 64];
+/// let storage = BumpStorage::from_uninit(&mut buffer);
+///
+/// let a = Box::new_in(1, &storage)?;
+/// let b = Box::new_in(2, &storage)?;
+/// let mut v = Vec::<u8, _>::new_in(&storage)?;
+/// v.extend_from_slice(&[3, 4, 5])?; // grows in place, because it is the last allocation
+/// let s = String::from_str_in("hello", &storage)?;
+///
+/// assert_eq!((*a, *b, &*v, &*s), (1, 2, &[3, 4, 5][..], "hello"));
+///
+/// # Ok(())
+/// # }
+/// ```
+pub struct BumpStorage<'a> {
+    start: NonNull<u8>,
+    end: NonNull<u8>,
+    /// the layout of the initial buffer if it was allocated from [`Global`]
+    owned: Option<Layout>,
+    chunks: Cell<Option<NonNull<ChunkHeader>>>,
+    chunk_end: Cell<NonNull<u8>>,
+    ptr: Cell<NonNull<u8>>,
+    chaining: bool,
+    _buffer: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+unsafe impl Send for BumpStorage<'_> {}
+
+impl<'a> BumpStorage<'a> {
+    /// Constructs a [`BumpStorage`] that allocates from an uninitialised `buffer`
+    pub fn from_uninit(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        let length = buffer.len();
+        let start = NonNull::from(buffer).cast::<u8>();
+        unsafe { Self::from_raw_parts(start, start.add(length), None, false) }
+    }
+
+    unsafe fn from_raw_parts(
+        start: NonNull<u8>,
+        end: NonNull<u8>,
+        owned: Option<Layout>,
+        chaining: bool,
+    ) -> Self {
+        Self {
+            start,
+            end,
+            owned,
+            chunks: Cell::new(None),
+            chunk_end: Cell::new(end),
+            ptr: Cell::new(start),
+            chaining,
+            _buffer: PhantomData,
+        }
+    }
+
+    /// Returns whether new chunks will be allocated from [`Global`] when the current one fills up
+    pub fn chaining(&self) -> bool {
+        self.chaining
+    }
+
+    /// Sets whether new chunks will be allocated from [`Global`] when the current one fills up
+    pub fn set_chaining(&mut self, chaining: bool) {
+        self.chaining = chaining;
+    }
+
+    /// Frees every allocation at once, and any chunks that were chained onto the initial buffer
+    pub fn reset(&mut self) {
+        unsafe { self.free_chunks() };
+        self.chunk_end.set(self.end);
+        self.ptr.set(self.start);
+    }
+
+    unsafe fn free_chunks(&self) {
+        let mut chunks = self.chunks.take();
+        while let Some(chunk) = chunks {
+            unsafe {
+                let ChunkHeader { previous, layout } = chunk.read();
+                Global.deallocate(layout, GlobalHandle(chunk.cast()));
+                chunks = previous;
+            }
+        }
+    }
+
+    fn bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let ptr = self.ptr.get();
+        let padding = ptr.align_offset(layout.align());
+        let available = self.chunk_end.get().addr().get() - ptr.addr().get();
+        if padding.checked_add(layout.size())? > available {
+            return None;
+        }
+
+        unsafe {
+            let start = ptr.add(padding);
+            self.ptr.set(start.add(layout.size()));
+            Some(start)
+        }
+    }
+
+    /// Returns whether the allocation at `ptr` with `size` bytes ends where the next allocation will be made,
+    /// this compares the end instead of the start so a zero size allocation is never mistaken for the allocation after it
+    fn is_last(&self, ptr: NonNull<u8>, size: usize) -> bool {
+        ptr.addr().get() + size == self.ptr.get().addr().get()
+    }
+
+    fn chain(&self, layout: Layout) -> Result<(), StorageAllocError> {
+        if !self.chaining {
+            return Err(StorageAllocError);
+        }
+
+        let previous_size = match self.chunks.get() {
+            Some(chunk) => unsafe { chunk.as_ref().layout.size() },
+            None => self.end.addr().get() - self.start.addr().get(),
+        };
+        let size = size_of::<ChunkHeader>()
+            .checked_add(layout.size())
+            .and_then(|size| size.checked_add(layout.align()))
+            .ok_or(StorageAllocError)?
+            .max(previous_size.saturating_mul(2))
+            .max(MIN_CHUNK_SIZE);
+        let chunk_layout = Layout::from_size_align(size, align_of::<ChunkHeader>())
+            .map_err(|_| StorageAllocError)?;
+
+        let (handle, _) = Global.allocate(chunk_layout)?;
+        let chunk = handle.0.cast::<ChunkHeader>();
+        unsafe {
+            chunk.write(ChunkHeader {
+                previous: self.chunks.get(),
+                layout: chunk_layout,
+            });
+            self.chunks.set(Some(chunk));
+            self.chunk_end.set(chunk.cast::<u8>().add(size));
+            self.ptr.set(chunk.add(1).cast());
+        }
+        Ok(())
+    }
+}
+
+impl BumpStorage<'static> {
+    /// Constructs a [`BumpStorage`] that allocates from a buffer of `capacity` bytes allocated from [`Global`]
+    ///
+    /// Chaining is enabled for the returned [`BumpStorage`]
+    pub fn with_capacity(capacity: usize) -> Result<Self, StorageAllocError> {
+        let layout = Layout::from_size_align(capacity, 1).map_err(|_| StorageAllocError)?;
+        let (handle, _) = Global.allocate(layout)?;
+        let start = handle.0.cast::<u8>();
+        Ok(unsafe { Self::from_raw_parts(start, start.add(capacity), Some(layout), true) })
+    }
+}
+
+unsafe impl Storage for BumpStorage<'_> {
+    type Handle = BumpStorageHandle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        handle.0
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let ptr = match self.bump(layout) {
+            Some(ptr) => ptr,
+            None => {
+                self.chain(layout)?;
+                self.bump(layout).ok_or(StorageAllocError)?
+            }
+        };
+        Ok((BumpStorageHandle(ptr.cast()), layout.size()))
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        let ptr = handle.0.cast::<u8>();
+        if self.is_last(ptr, layout.size()) {
+            self.ptr.set(ptr);
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let ptr = handle.0.cast::<u8>();
+        if self.is_last(ptr, old_layout.size())
+            && ptr.align_offset(new_layout.align()) == 0
+            && new_layout.size() <= self.chunk_end.get().addr().get() - ptr.addr().get()
+        {
+            self.ptr.set(unsafe { ptr.add(new_layout.size()) });
+            return Ok((handle, new_layout.size()));
+        }
+
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            new_handle
+                .0
+                .cast::<u8>()
+                .copy_from_nonoverlapping(ptr, old_layout.size());
+        }
+        Ok((new_handle, new_size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let ptr = handle.0.cast::<u8>();
+        if ptr.align_offset(new_layout.align()) == 0 {
+            if self.is_last(ptr, old_layout.size()) {
+                self.ptr.set(unsafe { ptr.add(new_layout.size()) });
+                return Ok((handle, new_layout.size()));
+            }
+            return Ok((handle, old_layout.size()));
+        }
+
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            new_handle
+                .0
+                .cast::<u8>()
+                .copy_from_nonoverlapping(ptr, new_layout.size());
+        }
+        Ok((new_handle, new_size))
+    }
+}
+
+unsafe impl MultipleStorage for BumpStorage<'_> {}
+unsafe impl StableStorage for BumpStorage<'_> {}
+unsafe impl PointerStorage for BumpStorage<'_> {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        BumpStorageHandle(ptr)
+    }
+}
+
+impl Drop for BumpStorage<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            self.free_chunks();
+            if let Some(layout) = self.owned {
+                Global.deallocate(layout, GlobalHandle(self.start.cast()));
+            }
+        }
+    }
+}
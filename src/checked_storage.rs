@@ -0,0 +1,212 @@
+extern crate alloc;
+
+use crate::{MultipleStorage, StableStorage, Storage, StorageAllocError, StorageHandle};
+use alloc::vec::Vec;
+use core::{alloc::Layout, cell::RefCell, ptr::NonNull};
+
+/// The [`StorageHandle`] for [`CheckedStorage`],
+/// this is the handle of the inner [`Storage`] along with the slot and generation used to check that it is still live
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CheckedStorageHandle<H> {
+    /// The handle from the inner [`Storage`]
+    pub handle: H,
+    /// The slot that keeps track of this allocation
+    pub slot: u32,
+    /// The generation of the slot when this handle was created
+    pub generation: u32,
+}
+
+impl<H: StorageHandle> StorageHandle for CheckedStorageHandle<H> {}
+
+struct Slot {
+    generation: u32,
+    live: bool,
+}
+
+#[derive(Default)]
+struct Slots {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+}
+
+/// A wrapper around a [`Storage`] that gives every allocation a slot with a generation,
+/// so that in debug builds using a handle after it was deallocated, grown or shrunk panics instead of being undefined behaviour
+///
+/// ```
+/// use storage_api::{CheckedStorage, Global, Vec};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let mut v = Vec::<i32, CheckedStorage<Global>>::new()?;
+/// v.extend_from_slice(&[1, 2, 3])?;
+/// assert_eq!(&*v, &[1, 2, 3]);
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Resolving a handle that was deallocated panics
+/// ```should_panic
+/// use core::alloc::Layout;
+/// use storage_api::{CheckedStorage, Global, Storage};
+///
+/// let storage = CheckedStorage::new(Global);
+/// let (handle, _) = storage.allocate(Layout::new::<u32>()).unwrap();
+/// unsafe { storage.deallocate(Layout::new::<u32>(), handle) };
+/// unsafe { storage.resolve(handle) }; // `handle` is stale
+/// ```
+pub struct CheckedStorage<S> {
+    storage: S,
+    slots: RefCell<Slots>,
+}
+
+impl<S> CheckedStorage<S> {
+    /// Constructs a new [`CheckedStorage`] wrapping `storage`
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage,
+            slots: RefCell::new(Slots {
+                slots: Vec::new(),
+                free: Vec::new(),
+            }),
+        }
+    }
+
+    /// Returns a reference to the inner [`Storage`]
+    pub fn inner(&self) -> &S {
+        &self.storage
+    }
+
+    /// Returns the number of allocations that havent been deallocated yet
+    pub fn live_allocations(&self) -> usize {
+        let slots = self.slots.borrow();
+        slots.slots.len() - slots.free.len()
+    }
+
+    /// Returns whether `handle` refers to a live allocation
+    pub fn is_live<H>(&self, handle: CheckedStorageHandle<H>) -> bool {
+        self.slots
+            .borrow()
+            .slots
+            .get(handle.slot as usize)
+            .is_some_and(|slot| slot.live && slot.generation == handle.generation)
+    }
+
+    #[track_caller]
+    fn check<H: StorageHandle>(&self, handle: CheckedStorageHandle<H>, operation: &str) {
+        if cfg!(debug_assertions) && !self.is_live(handle) {
+            panic!("{operation} was called with {handle:?}, which is not a live allocation");
+        }
+    }
+
+    fn insert<H>(&self, handle: H) -> CheckedStorageHandle<H> {
+        let mut slots = self.slots.borrow_mut();
+        let slot = match slots.free.pop() {
+            Some(slot) => slot,
+            None => {
+                let slot = u32::try_from(slots.slots.len())
+                    .expect("a CheckedStorage can only track u32::MAX allocations at once");
+                slots.slots.push(Slot {
+                    generation: 0,
+                    live: false,
+                });
+                slot
+            }
+        };
+
+        let entry = &mut slots.slots[slot as usize];
+        entry.live = true;
+        CheckedStorageHandle {
+            handle,
+            slot,
+            generation: entry.generation,
+        }
+    }
+
+    /// Makes every existing handle to `slot` stale, and returns the new generation
+    fn bump(&self, slot: u32) -> u32 {
+        let mut slots = self.slots.borrow_mut();
+        let entry = &mut slots.slots[slot as usize];
+        entry.generation = entry.generation.wrapping_add(1);
+        entry.generation
+    }
+
+    fn remove(&self, slot: u32) {
+        self.bump(slot);
+        let mut slots = self.slots.borrow_mut();
+        slots.slots[slot as usize].live = false;
+        slots.free.push(slot);
+    }
+}
+
+unsafe impl<S: Storage> Storage for CheckedStorage<S> {
+    type Handle = CheckedStorageHandle<S::Handle>;
+
+    #[track_caller]
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        self.check(handle, "resolve");
+        unsafe { self.storage.resolve(handle.handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let (handle, size) = self.storage.allocate(layout)?;
+        Ok((self.insert(handle), size))
+    }
+
+    #[track_caller]
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        self.check(handle, "deallocate");
+        unsafe { self.storage.deallocate(layout, handle.handle) };
+        self.remove(handle.slot);
+    }
+
+    #[track_caller]
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        self.check(handle, "grow");
+        let (new_handle, size) =
+            unsafe { self.storage.grow(old_layout, new_layout, handle.handle)? };
+        Ok((
+            CheckedStorageHandle {
+                handle: new_handle,
+                slot: handle.slot,
+                generation: self.bump(handle.slot),
+            },
+            size,
+        ))
+    }
+
+    #[track_caller]
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        self.check(handle, "shrink");
+        let (new_handle, size) =
+            unsafe { self.storage.shrink(old_layout, new_layout, handle.handle)? };
+        Ok((
+            CheckedStorageHandle {
+                handle: new_handle,
+                slot: handle.slot,
+                generation: self.bump(handle.slot),
+            },
+            size,
+        ))
+    }
+}
+
+unsafe impl<S: MultipleStorage> MultipleStorage for CheckedStorage<S> {}
+unsafe impl<S: StableStorage> StableStorage for CheckedStorage<S> {}
+
+impl<S: Default> Default for CheckedStorage<S> {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
@@ -0,0 +1,279 @@
+extern crate alloc;
+
+use crate::{MultipleStorage, Storage, StorageAllocError, StorageHandle};
+use alloc::vec::Vec;
+use core::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ptr::NonNull,
+};
+
+/// The [`StorageHandle`] for [`CompactingStorage`],
+/// this is an index into a table that stores where the allocation currently is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompactingStorageHandle(u32);
+
+impl StorageHandle for CompactingStorageHandle {}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    offset: usize,
+    size: usize,
+    align: usize,
+}
+
+#[derive(Default)]
+struct Table {
+    /// [`None`] for entries that arent being used
+    entries: Vec<Option<Entry>>,
+    free: Vec<u32>,
+}
+
+/// A [`Storage`] that allocates by bumping an offset through a buffer, and can move allocations to remove the space left by freed allocations with [`CompactingStorage::compact`]
+///
+/// Handles are indices into a table of where every allocation is, so they stay valid when allocations are moved, and growing or shrinking never changes the handle.
+/// This works because collections always go through [`Storage::resolve`] instead of keeping pointers to their allocations
+///
+/// Pointers from [`Storage::resolve`] are invalidated by [`CompactingStorage::compact`], which is why it takes `&mut self`,
+/// so collections that use a `&CompactingStorage` must be turned into their raw parts (which keep the handle) before compacting and reconstructed after.
+/// For the same reason this doesnt implement [`StableStorage`](crate::StableStorage), which promises that pointers from [`Storage::resolve`] stay valid,
+/// or [`PointerStorage`](crate::PointerStorage).
+/// `&CompactingStorage` and `&mut CompactingStorage` do implement [`StableStorage`](crate::StableStorage) like every reference to a storage does,
+/// this is only sound because [`CompactingStorage::compact`] takes `&mut self`, so it cant be called while a reference is being used as a storage
+///
+/// ```
+/// use core::mem::MaybeUninit;
+/// use storage_api::{CompactingStorage, Vec};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let mut buffer = [MaybeUninit::uninit(); 256];
+/// let mut storage = CompactingStorage::from_uninit(&mut buffer);
+///
+/// let mut parts = std::vec::Vec::new();
+/// for i in 0..8 {
+///     let mut v = Vec::<u8, _>::with_capacity_in(32, &storage)?;
+///     v.extend_from_slice(&[i; 32])?;
+///     let (_, handle, length, capacity) = v.into_raw_parts();
+///     parts.push((i, handle, length, capacity));
+/// }
+///
+/// // free every other allocation, half of the buffer is free but it is fragmented
+/// for (_, handle, length, capacity) in parts.extract_if(.., |(i, ..)| *i % 2 == 0) {
+///     drop(unsafe { Vec::<u8, _>::from_raw_parts(&storage, handle, length, capacity) });
+/// }
+/// assert!(Vec::<u8, _>::with_capacity_in(64, &storage).is_err());
+///
+/// storage.compact();
+/// assert!(Vec::<u8, _>::with_capacity_in(128, &storage).is_ok());
+/// for &(i, handle, length, capacity) in &parts {
+///     let v = unsafe { Vec::<u8, _>::from_raw_parts(&storage, handle, length, capacity) };
+///     assert_eq!(&*v, &[i; 32]);
+/// }
+///
+/// # Ok(())
+/// # }
+/// ```
+pub struct CompactingStorage<'a> {
+    base: NonNull<u8>,
+    capacity: usize,
+    top: Cell<usize>,
+    live_bytes: Cell<usize>,
+    table: RefCell<Table>,
+    _buffer: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+unsafe impl Send for CompactingStorage<'_> {}
+
+impl<'a> CompactingStorage<'a> {
+    /// Constructs a [`CompactingStorage`] that allocates from an uninitialised `buffer`
+    pub fn from_uninit(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        Self {
+            capacity: buffer.len(),
+            base: NonNull::from(buffer).cast(),
+            top: Cell::new(0),
+            live_bytes: Cell::new(0),
+            table: RefCell::new(Table::default()),
+            _buffer: PhantomData,
+        }
+    }
+
+    /// Returns the size of the buffer in bytes
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of bytes that have been used, including the space left by freed allocations and padding for alignment
+    pub fn used(&self) -> usize {
+        self.top.get()
+    }
+
+    /// Returns the number of bytes in live allocations
+    pub fn live_bytes(&self) -> usize {
+        self.live_bytes.get()
+    }
+
+    /// Moves every live allocation towards the start of the buffer, so that all the free space is after them
+    ///
+    /// Handles stay valid, but all pointers from [`Storage::resolve`] are invalidated
+    pub fn compact(&mut self) {
+        let table = self.table.get_mut();
+        let mut live = table
+            .entries
+            .iter_mut()
+            .filter_map(Option::as_mut)
+            .collect::<Vec<_>>();
+        live.sort_unstable_by_key(|entry| entry.offset);
+
+        let mut top = 0;
+        for entry in live {
+            // `top` is never past the start of an allocation that isnt zero sized, and `entry.offset` is already aligned,
+            // so this only moves allocations backwards, zero sized allocations can be inside other allocations so they are clamped to where they are
+            let offset = Self::align_offset(self.base, top, entry.align)
+                .map_or(entry.offset, |offset| offset.min(entry.offset));
+            if offset != entry.offset {
+                unsafe {
+                    self.base
+                        .add(offset)
+                        .copy_from(self.base.add(entry.offset), entry.size)
+                };
+                entry.offset = offset;
+            }
+            // zero sized allocations can be inside other allocations, so they dont move the end of the used part
+            if entry.size != 0 {
+                top = offset + entry.size;
+            }
+        }
+        self.top.set(top);
+    }
+
+    /// Returns the first offset at or after `offset` that is aligned to `align`
+    fn align_offset(base: NonNull<u8>, offset: usize, align: usize) -> Option<usize> {
+        let address = base.addr().get().checked_add(offset)?;
+        Some(address.checked_next_multiple_of(align)? - base.addr().get())
+    }
+
+    /// Reserves `layout.size()` bytes at the end of the used part of the buffer
+    fn bump(&self, layout: Layout) -> Result<usize, StorageAllocError> {
+        let offset = Self::align_offset(self.base, self.top.get(), layout.align())
+            .ok_or(StorageAllocError)?;
+        let end = offset.checked_add(layout.size()).ok_or(StorageAllocError)?;
+        if end > self.capacity {
+            return Err(StorageAllocError);
+        }
+        self.top.set(end);
+        Ok(offset)
+    }
+
+    /// Frees the bytes of `entry`, this only makes the space available again if it is the last allocation
+    fn release(&self, entry: Entry) {
+        if entry.offset + entry.size == self.top.get() {
+            self.top.set(entry.offset);
+        }
+    }
+
+    /// Resizes the allocation for `handle` to `new_layout`, moving it to the end of the buffer if it doesnt fit where it is
+    unsafe fn resize(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: CompactingStorageHandle,
+    ) -> Result<(CompactingStorageHandle, usize), StorageAllocError> {
+        let mut table = self.table.borrow_mut();
+        let entry = unsafe { table.entries[handle.0 as usize].as_mut().unwrap_unchecked() };
+        let aligned = (self.base.addr().get() + entry.offset).is_multiple_of(new_layout.align());
+        let is_last = entry.offset + entry.size == self.top.get();
+
+        if aligned && (new_layout.size() <= entry.size || is_last) {
+            let end = entry
+                .offset
+                .checked_add(new_layout.size())
+                .ok_or(StorageAllocError)?;
+            if end > self.capacity {
+                return Err(StorageAllocError);
+            }
+            if is_last {
+                self.top.set(end);
+            }
+        } else {
+            let offset = self.bump(new_layout)?;
+            unsafe {
+                self.base.add(offset).copy_from_nonoverlapping(
+                    self.base.add(entry.offset),
+                    old_layout.size().min(new_layout.size()),
+                );
+            }
+            self.release(*entry);
+            entry.offset = offset;
+        }
+
+        self.live_bytes
+            .set(self.live_bytes.get() - entry.size + new_layout.size());
+        entry.size = new_layout.size();
+        entry.align = new_layout.align();
+        Ok((handle, new_layout.size()))
+    }
+}
+
+unsafe impl Storage for CompactingStorage<'_> {
+    type Handle = CompactingStorageHandle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        let table = self.table.borrow();
+        let entry = unsafe { table.entries[handle.0 as usize].unwrap_unchecked() };
+        unsafe { self.base.add(entry.offset).cast() }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let mut table = self.table.borrow_mut();
+        let index = match table.free.last() {
+            Some(&index) => index,
+            None => u32::try_from(table.entries.len()).map_err(|_| StorageAllocError)?,
+        };
+
+        let entry = Entry {
+            offset: self.bump(layout)?,
+            size: layout.size(),
+            align: layout.align(),
+        };
+        if table.free.pop().is_some() {
+            table.entries[index as usize] = Some(entry);
+        } else {
+            table.entries.push(Some(entry));
+        }
+        self.live_bytes.set(self.live_bytes.get() + layout.size());
+        Ok((CompactingStorageHandle(index), layout.size()))
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        _ = layout;
+        let mut table = self.table.borrow_mut();
+        let entry = unsafe { table.entries[handle.0 as usize].take().unwrap_unchecked() };
+        table.free.push(handle.0);
+        self.release(entry);
+        self.live_bytes.set(self.live_bytes.get() - entry.size);
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        unsafe { self.resize(old_layout, new_layout, handle) }
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        unsafe { self.resize(old_layout, new_layout, handle) }
+    }
+}
+
+unsafe impl MultipleStorage for CompactingStorage<'_> {}
@@ -0,0 +1,227 @@
+use crate::{
+    Global, MultipleStorage, PointerStorage, StableStorage, Storage, StorageAllocError,
+    StorageHandle, global_storage::GlobalHandle,
+};
+use core::{
+    alloc::Layout,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The [`StorageHandle`] for [`ConcurrentBumpStorage`],
+/// this is a wrapper around a pointer to the allocation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConcurrentBumpStorageHandle(NonNull<()>);
+
+unsafe impl Send for ConcurrentBumpStorageHandle {}
+unsafe impl Sync for ConcurrentBumpStorageHandle {}
+
+impl StorageHandle for ConcurrentBumpStorageHandle {}
+
+/// An arena like [`BumpStorage`](crate::BumpStorage) that can be allocated from by several threads at once without locking,
+/// by bumping an atomic offset through a buffer
+///
+/// Deallocating does nothing unless the allocation was the last one made, all allocations are freed at once with [`ConcurrentBumpStorage::reset`]
+///
+/// ```
+/// use storage_api::{Box, ConcurrentBumpStorage, Vec};
+///
+/// let mut storage = ConcurrentBumpStorage::with_capacity(4096).unwrap();
+///
+/// for _frame in 0..3 {
+///     std::thread::scope(|scope| {
+///         for i in 0..4u32 {
+///             let storage = &storage;
+///             scope.spawn(move || {
+///                 let b = Box::new_in(i, storage).unwrap();
+///                 let mut v = Vec::<u32, _>::new_in(storage).unwrap();
+///                 v.extend_from_slice(&[*b; 8]).unwrap();
+///                 assert!(v.iter().all(|&x| x == i));
+///             });
+///         }
+///     });
+///     storage.reset();
+/// }
+/// ```
+pub struct ConcurrentBumpStorage<'a> {
+    start: NonNull<u8>,
+    capacity: usize,
+    /// the layout of the buffer if it was allocated from [`Global`]
+    owned: Option<Layout>,
+    offset: AtomicUsize,
+    _buffer: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+unsafe impl Send for ConcurrentBumpStorage<'_> {}
+unsafe impl Sync for ConcurrentBumpStorage<'_> {}
+
+impl<'a> ConcurrentBumpStorage<'a> {
+    /// Constructs a [`ConcurrentBumpStorage`] that allocates from an uninitialised `buffer`
+    pub fn from_uninit(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        let capacity = buffer.len();
+        Self {
+            start: NonNull::from(buffer).cast(),
+            capacity,
+            owned: None,
+            offset: AtomicUsize::new(0),
+            _buffer: PhantomData,
+        }
+    }
+
+    /// Returns the size of the buffer in bytes
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of bytes that have been used, including padding for alignment
+    pub fn used(&self) -> usize {
+        self.offset.load(Ordering::Relaxed)
+    }
+
+    /// Frees every allocation at once
+    pub fn reset(&mut self) {
+        *self.offset.get_mut() = 0;
+    }
+
+    /// Returns the offset of `ptr` from the start of the buffer
+    fn offset_of(&self, ptr: NonNull<()>) -> usize {
+        ptr.addr().get() - self.start.addr().get()
+    }
+
+    /// Tries to move the end of the allocation at `offset` from `old_size` to `new_size`, this only works if it is the last allocation
+    fn resize_last(&self, offset: usize, old_size: usize, new_size: usize) -> bool {
+        new_size <= self.capacity - offset
+            && self
+                .offset
+                .compare_exchange(
+                    offset + old_size,
+                    offset + new_size,
+                    Ordering::AcqRel,
+                    Ordering::Relaxed,
+                )
+                .is_ok()
+    }
+}
+
+impl ConcurrentBumpStorage<'static> {
+    /// Constructs a [`ConcurrentBumpStorage`] that allocates from a buffer of `capacity` bytes allocated from [`Global`]
+    pub fn with_capacity(capacity: usize) -> Result<Self, StorageAllocError> {
+        let layout = Layout::from_size_align(capacity, 1).map_err(|_| StorageAllocError)?;
+        let (handle, _) = Global.allocate(layout)?;
+        Ok(Self {
+            start: handle.0.cast(),
+            capacity,
+            owned: Some(layout),
+            offset: AtomicUsize::new(0),
+            _buffer: PhantomData,
+        })
+    }
+}
+
+unsafe impl Storage for ConcurrentBumpStorage<'_> {
+    type Handle = ConcurrentBumpStorageHandle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        handle.0
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let mut offset = self.offset.load(Ordering::Acquire);
+        loop {
+            let padding = unsafe { self.start.add(offset) }.align_offset(layout.align());
+            let start = offset.checked_add(padding).ok_or(StorageAllocError)?;
+            let end = start.checked_add(layout.size()).ok_or(StorageAllocError)?;
+            if end > self.capacity {
+                return Err(StorageAllocError);
+            }
+
+            match self.offset.compare_exchange_weak(
+                offset,
+                end,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    let ptr = unsafe { self.start.add(start) };
+                    return Ok((ConcurrentBumpStorageHandle(ptr.cast()), layout.size()));
+                }
+                Err(current) => offset = current,
+            }
+        }
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        self.resize_last(self.offset_of(handle.0), layout.size(), 0);
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        if handle.0.align_offset(new_layout.align()) == 0
+            && self.resize_last(
+                self.offset_of(handle.0),
+                old_layout.size(),
+                new_layout.size(),
+            )
+        {
+            return Ok((handle, new_layout.size()));
+        }
+
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            new_handle
+                .0
+                .cast::<u8>()
+                .copy_from_nonoverlapping(handle.0.cast(), old_layout.size());
+        }
+        Ok((new_handle, new_size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        if handle.0.align_offset(new_layout.align()) == 0 {
+            if self.resize_last(
+                self.offset_of(handle.0),
+                old_layout.size(),
+                new_layout.size(),
+            ) {
+                return Ok((handle, new_layout.size()));
+            }
+            return Ok((handle, old_layout.size()));
+        }
+
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            new_handle
+                .0
+                .cast::<u8>()
+                .copy_from_nonoverlapping(handle.0.cast(), new_layout.size());
+        }
+        Ok((new_handle, new_size))
+    }
+}
+
+unsafe impl MultipleStorage for ConcurrentBumpStorage<'_> {}
+unsafe impl StableStorage for ConcurrentBumpStorage<'_> {}
+unsafe impl PointerStorage for ConcurrentBumpStorage<'_> {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        ConcurrentBumpStorageHandle(ptr)
+    }
+}
+
+impl Drop for ConcurrentBumpStorage<'_> {
+    fn drop(&mut self) {
+        if let Some(layout) = self.owned {
+            unsafe { Global.deallocate(layout, GlobalHandle(self.start.cast())) };
+        }
+    }
+}
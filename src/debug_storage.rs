@@ -0,0 +1,207 @@
+use crate::{MultipleStorage, StableStorage, Storage, StorageAllocError, StorageHandle};
+use core::{alloc::Layout, ptr::NonNull};
+
+/// The [`StorageHandle`] for [`DebugStorage`],
+/// this is the handle of the inner [`Storage`] along with where the allocation starts after the front canary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DebugStorageHandle<H> {
+    handle: H,
+    offset: usize,
+}
+
+impl<H: StorageHandle> StorageHandle for DebugStorageHandle<H> {}
+
+/// A wrapper around a [`Storage`] for finding memory bugs in unsafe code
+///
+/// - new allocations (and the new part of grown allocations) are filled with [`DebugStorage::FRESH`]
+/// - deallocated memory, and the memory left behind by [`Storage::grow`] and [`Storage::shrink`], is filled with [`DebugStorage::POISON`]
+/// - every allocation has [`DebugStorage::CANARY_SIZE`] bytes of [`DebugStorage::CANARY`] before and after it,
+///   these are checked when the allocation is deallocated, grown or shrunk, and it panics if any were overwritten
+///
+/// Grown and shrunk allocations are always moved, so pointers that are still used after a reallocation read poisoned memory,
+/// this means the inner [`Storage`] must be a [`MultipleStorage`] with room for both the old and new allocation at once
+///
+/// ```
+/// use core::alloc::Layout;
+/// use storage_api::{DebugStorage, Global, Storage, Vec};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let storage = DebugStorage::new(Global);
+/// let layout = Layout::new::<[u8; 4]>();
+/// let (handle, _) = storage.allocate(layout)?;
+/// let bytes = unsafe { storage.resolve(handle).cast::<[u8; 4]>().read() };
+/// assert_eq!(bytes, [DebugStorage::<Global>::FRESH; 4]);
+/// unsafe { storage.deallocate(layout, handle) };
+///
+/// let mut v = Vec::<i32, _>::new_in(&storage)?;
+/// v.extend_from_slice(&[1, 2, 3])?;
+/// assert_eq!(&*v, &[1, 2, 3]);
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Writing past the end of an allocation is caught when it is deallocated
+/// ```should_panic
+/// use core::alloc::Layout;
+/// use storage_api::{DebugStorage, Global, Storage};
+///
+/// let storage = DebugStorage::new(Global);
+/// let layout = Layout::new::<[u8; 4]>();
+/// let (handle, _) = storage.allocate(layout).unwrap();
+/// unsafe {
+///     storage.resolve(handle).cast::<u8>().add(4).write(5); // out of bounds
+///     storage.deallocate(layout, handle); // this panics
+/// }
+/// ```
+#[derive(Default, Clone, Copy)]
+pub struct DebugStorage<S> {
+    storage: S,
+}
+
+impl<S> DebugStorage<S> {
+    /// The byte that new allocations are filled with
+    pub const FRESH: u8 = 0xCD;
+    /// The byte that deallocated memory is filled with
+    pub const POISON: u8 = 0xDD;
+    /// The byte that the canaries around every allocation are filled with
+    pub const CANARY: u8 = 0xFD;
+    /// The minimum number of canary bytes on each side of an allocation
+    ///
+    /// There may be more before an allocation with an alignment greater than this
+    pub const CANARY_SIZE: usize = 16;
+
+    /// Constructs a new [`DebugStorage`] wrapping `storage`
+    pub const fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Returns a reference to the inner [`Storage`]
+    pub fn inner(&self) -> &S {
+        &self.storage
+    }
+
+    /// Returns the inner [`Storage`]
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    /// Returns the [`Layout`] of the allocation in the inner [`Storage`] and the offset of the allocation within it
+    fn inner_layout(layout: Layout) -> Result<(Layout, usize), StorageAllocError> {
+        let offset = Self::CANARY_SIZE.max(layout.align());
+        let size = offset
+            .checked_add(layout.size())
+            .and_then(|size| size.checked_add(Self::CANARY_SIZE))
+            .ok_or(StorageAllocError)?;
+        let layout =
+            Layout::from_size_align(size, layout.align()).map_err(|_| StorageAllocError)?;
+        Ok((layout, offset))
+    }
+}
+
+impl<S: MultipleStorage> DebugStorage<S> {
+    fn allocate_inner(
+        &self,
+        layout: Layout,
+    ) -> Result<DebugStorageHandle<S::Handle>, StorageAllocError> {
+        let (inner_layout, offset) = Self::inner_layout(layout)?;
+        let (handle, _) = self.storage.allocate(inner_layout)?;
+        unsafe {
+            let ptr = self.storage.resolve(handle).cast::<u8>();
+            ptr.write_bytes(Self::CANARY, offset);
+            ptr.add(offset).write_bytes(Self::FRESH, layout.size());
+            ptr.add(offset + layout.size())
+                .write_bytes(Self::CANARY, Self::CANARY_SIZE);
+        }
+        Ok(DebugStorageHandle { handle, offset })
+    }
+
+    #[track_caller]
+    unsafe fn deallocate_inner(&self, layout: Layout, handle: DebugStorageHandle<S::Handle>) {
+        unsafe {
+            let ptr = self.storage.resolve(handle.handle).cast::<u8>();
+            let check = |range: core::ops::Range<usize>| {
+                for i in range {
+                    if ptr.add(i).read() != Self::CANARY {
+                        panic!(
+                            "the canary of {handle:?} with {layout:?} was overwritten at offset {} from the start of the allocation",
+                            i as isize - handle.offset as isize,
+                        );
+                    }
+                }
+            };
+            check(0..handle.offset);
+            check(handle.offset + layout.size()..handle.offset + layout.size() + Self::CANARY_SIZE);
+
+            let inner_layout = Layout::from_size_align_unchecked(
+                handle.offset + layout.size() + Self::CANARY_SIZE,
+                layout.align(),
+            );
+            ptr.write_bytes(Self::POISON, inner_layout.size());
+            self.storage.deallocate(inner_layout, handle.handle);
+        }
+    }
+
+    #[track_caller]
+    unsafe fn reallocate(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: DebugStorageHandle<S::Handle>,
+    ) -> Result<(DebugStorageHandle<S::Handle>, usize), StorageAllocError> {
+        let new_handle = self.allocate_inner(new_layout)?;
+        unsafe {
+            self.resolve(new_handle)
+                .cast::<u8>()
+                .copy_from_nonoverlapping(
+                    self.resolve(handle).cast(),
+                    old_layout.size().min(new_layout.size()),
+                );
+            self.deallocate_inner(old_layout, handle);
+            Ok((new_handle, new_layout.size()))
+        }
+    }
+}
+
+unsafe impl<S: MultipleStorage> Storage for DebugStorage<S> {
+    type Handle = DebugStorageHandle<S::Handle>;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { self.storage.resolve(handle.handle).byte_add(handle.offset) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let handle = self.allocate_inner(layout)?;
+        Ok((handle, layout.size()))
+    }
+
+    #[track_caller]
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        unsafe { self.deallocate_inner(layout, handle) }
+    }
+
+    #[track_caller]
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        unsafe { self.reallocate(old_layout, new_layout, handle) }
+    }
+
+    #[track_caller]
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        unsafe { self.reallocate(old_layout, new_layout, handle) }
+    }
+}
+
+unsafe impl<S: MultipleStorage> MultipleStorage for DebugStorage<S> {}
+unsafe impl<S: MultipleStorage + StableStorage> StableStorage for DebugStorage<S> {}
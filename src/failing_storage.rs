@@ -0,0 +1,227 @@
+use crate::{Global, MultipleStorage, PointerStorage, StableStorage, Storage, StorageAllocError};
+use core::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    ptr::NonNull,
+};
+
+/// Decides which requests a [`FailingStorage`] should fail
+///
+/// This is implemented for closures taking the index of the request and its [`Layout`]
+pub trait FailurePolicy {
+    /// Returns whether the request should fail
+    ///
+    /// `index` is the number of requests that came before this one, including ones that failed
+    fn should_fail(&mut self, index: usize, layout: Layout) -> bool;
+}
+
+impl<F: FnMut(usize, Layout) -> bool> FailurePolicy for F {
+    fn should_fail(&mut self, index: usize, layout: Layout) -> bool {
+        self(index, layout)
+    }
+}
+
+/// A [`FailurePolicy`] that never fails
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FailNever;
+
+impl FailurePolicy for FailNever {
+    fn should_fail(&mut self, _index: usize, _layout: Layout) -> bool {
+        false
+    }
+}
+
+/// A [`FailurePolicy`] that fails only the request with index `N` (counting from 0)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FailNth(pub usize);
+
+impl FailurePolicy for FailNth {
+    fn should_fail(&mut self, index: usize, _layout: Layout) -> bool {
+        index == self.0
+    }
+}
+
+/// A [`FailurePolicy`] that fails every `N`th request, so `FailEveryNth(3)` fails requests 2, 5, 8, ...
+///
+/// `FailEveryNth(0)` never fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FailEveryNth(pub usize);
+
+impl FailurePolicy for FailEveryNth {
+    fn should_fail(&mut self, index: usize, _layout: Layout) -> bool {
+        self.0 != 0 && (index + 1).is_multiple_of(self.0)
+    }
+}
+
+/// A [`FailurePolicy`] that fails every request for more than `N` bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FailLargerThan(pub usize);
+
+impl FailurePolicy for FailLargerThan {
+    fn should_fail(&mut self, _index: usize, layout: Layout) -> bool {
+        layout.size() > self.0
+    }
+}
+
+/// A [`FailurePolicy`] that fails requests at random, using a seeded xorshift generator so failures are reproducible
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FailRandomly {
+    state: u64,
+    one_in: u64,
+}
+
+impl FailRandomly {
+    /// Constructs a new [`FailRandomly`] that fails on average one in every `one_in` requests
+    ///
+    /// The same `seed` always fails the same requests, `one_in` of 0 never fails
+    pub const fn new(seed: u64, one_in: u64) -> Self {
+        Self {
+            // xorshift gets stuck on 0
+            state: if seed == 0 {
+                0x9E37_79B9_7F4A_7C15
+            } else {
+                seed
+            },
+            one_in,
+        }
+    }
+}
+
+impl FailurePolicy for FailRandomly {
+    fn should_fail(&mut self, _index: usize, _layout: Layout) -> bool {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.one_in != 0 && self.state.is_multiple_of(self.one_in)
+    }
+}
+
+/// A wrapper around a [`Storage`] that fails some requests on purpose, for testing code that handles allocation failure
+///
+/// Every call to [`Storage::allocate`] and [`Storage::grow`] is a request, the [`FailurePolicy`] decides which ones fail,
+/// [`Storage::shrink`] and [`Storage::deallocate`] are always forwarded
+///
+/// ```
+/// use storage_api::{FailingStorage, Global, Vec, storages::FailLargerThan};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let storage = FailingStorage::new(Global, FailLargerThan(8));
+/// let mut v = Vec::<i32, _>::new_in(&storage)?;
+/// v.push(1).unwrap();
+/// v.push(2).unwrap();
+/// let error = v.push(3).unwrap_err(); // this needs room for more than 8 bytes
+/// assert_eq!(error.value, 3);
+/// assert_eq!(&*v, &[1, 2]);
+/// assert!(storage.failures() > 0);
+///
+/// # Ok(())
+/// # }
+/// ```
+pub struct FailingStorage<S = Global, P = FailNever> {
+    storage: S,
+    policy: RefCell<P>,
+    requests: Cell<usize>,
+    failures: Cell<usize>,
+}
+
+impl<S, P> FailingStorage<S, P> {
+    /// Constructs a new [`FailingStorage`] that forwards to `storage` unless `policy` says the request should fail
+    pub const fn new(storage: S, policy: P) -> Self {
+        Self {
+            storage,
+            policy: RefCell::new(policy),
+            requests: Cell::new(0),
+            failures: Cell::new(0),
+        }
+    }
+
+    /// Returns the number of requests so far
+    pub fn requests(&self) -> usize {
+        self.requests.get()
+    }
+
+    /// Returns the number of requests that were failed on purpose
+    pub fn failures(&self) -> usize {
+        self.failures.get()
+    }
+
+    /// Replaces the [`FailurePolicy`], the request count keeps going from where it was
+    pub fn set_policy(&mut self, policy: P) {
+        *self.policy.get_mut() = policy;
+    }
+
+    /// Returns a reference to the inner [`Storage`]
+    pub fn inner(&self) -> &S {
+        &self.storage
+    }
+
+    /// Splits the [`FailingStorage`] into the inner [`Storage`] and the [`FailurePolicy`]
+    pub fn into_inner(self) -> (S, P) {
+        (self.storage, self.policy.into_inner())
+    }
+}
+
+impl<S, P: FailurePolicy> FailingStorage<S, P> {
+    fn check(&self, layout: Layout) -> Result<(), StorageAllocError> {
+        let index = self.requests.get();
+        self.requests.set(index + 1);
+        if self.policy.borrow_mut().should_fail(index, layout) {
+            self.failures.set(self.failures.get() + 1);
+            Err(StorageAllocError)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+unsafe impl<S: Storage, P: FailurePolicy> Storage for FailingStorage<S, P> {
+    type Handle = S::Handle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { self.storage.resolve(handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        self.check(layout)?;
+        self.storage.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        unsafe { self.storage.deallocate(layout, handle) }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        self.check(new_layout)?;
+        unsafe { self.storage.grow(old_layout, new_layout, handle) }
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        unsafe { self.storage.shrink(old_layout, new_layout, handle) }
+    }
+}
+
+unsafe impl<S: MultipleStorage, P: FailurePolicy> MultipleStorage for FailingStorage<S, P> {}
+unsafe impl<S: StableStorage, P: FailurePolicy> StableStorage for FailingStorage<S, P> {}
+unsafe impl<S: PointerStorage, P: FailurePolicy> PointerStorage for FailingStorage<S, P> {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        unsafe { self.storage.handle_from_ptr(ptr) }
+    }
+}
+
+impl<S: Default, P: Default> Default for FailingStorage<S, P> {
+    fn default() -> Self {
+        Self::new(Default::default(), Default::default())
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{
+    MultipleStorage, ShareableStorage, StableStorage, Storage, StorageAllocError, StorageHandle,
+};
+use core::{alloc::Layout, ptr::NonNull};
+
+/// The [`StorageHandle`] for [`FallbackStorage`],
+/// this remembers which [`Storage`] the allocation is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FallbackStorageHandle<A, B> {
+    /// The allocation is in the primary [`Storage`]
+    Primary(A),
+    /// The allocation is in the secondary [`Storage`]
+    Secondary(B),
+}
+
+impl<A: StorageHandle, B: StorageHandle> StorageHandle for FallbackStorageHandle<A, B> {}
+
+/// A [`Storage`] that tries to allocate from a primary [`Storage`], and uses a secondary [`Storage`] if that fails
+///
+/// If an allocation in the primary [`Storage`] cant be grown, it is moved to the secondary [`Storage`]
+///
+/// ```
+/// use core::mem::MaybeUninit;
+/// use storage_api::{BumpStorage, FallbackStorage, Global, Vec};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let mut buffer = [MaybeUninit::uninit(); 32];
+/// let storage = FallbackStorage::new(BumpStorage::from_uninit(&mut buffer), Global);
+///
+/// let mut a = Vec::<u32, _>::with_capacity_in(6, &storage)?; // this uses most of the buffer
+/// let mut b = Vec::<u32, _>::with_capacity_in(4, &storage)?; // so this is allocated from `Global`
+/// a.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7])?; // and this moves `a` to `Global` as well
+/// b.extend_from_slice(&[8, 9])?;
+/// assert_eq!((&*a, &*b), (&[1, 2, 3, 4, 5, 6, 7][..], &[8, 9][..]));
+///
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default, Clone, Copy)]
+pub struct FallbackStorage<A, B> {
+    primary: A,
+    secondary: B,
+}
+
+impl<A, B> FallbackStorage<A, B> {
+    /// Constructs a new [`FallbackStorage`] from its primary and secondary [`Storage`]s
+    pub const fn new(primary: A, secondary: B) -> Self {
+        Self { primary, secondary }
+    }
+
+    /// Returns a reference to the primary [`Storage`]
+    pub fn primary(&self) -> &A {
+        &self.primary
+    }
+
+    /// Returns a reference to the secondary [`Storage`]
+    pub fn secondary(&self) -> &B {
+        &self.secondary
+    }
+
+    /// Splits the [`FallbackStorage`] into its primary and secondary [`Storage`]s
+    pub fn into_inner(self) -> (A, B) {
+        (self.primary, self.secondary)
+    }
+}
+
+unsafe impl<A: Storage, B: Storage> Storage for FallbackStorage<A, B> {
+    type Handle = FallbackStorageHandle<A::Handle, B::Handle>;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        match handle {
+            FallbackStorageHandle::Primary(handle) => unsafe { self.primary.resolve(handle) },
+            FallbackStorageHandle::Secondary(handle) => unsafe { self.secondary.resolve(handle) },
+        }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        match self.primary.allocate(layout) {
+            Ok((handle, size)) => Ok((FallbackStorageHandle::Primary(handle), size)),
+            Err(StorageAllocError) => {
+                let (handle, size) = self.secondary.allocate(layout)?;
+                Ok((FallbackStorageHandle::Secondary(handle), size))
+            }
+        }
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        match handle {
+            FallbackStorageHandle::Primary(handle) => unsafe {
+                self.primary.deallocate(layout, handle)
+            },
+            FallbackStorageHandle::Secondary(handle) => unsafe {
+                self.secondary.deallocate(layout, handle)
+            },
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        match handle {
+            FallbackStorageHandle::Primary(handle) => {
+                if let Ok((handle, size)) =
+                    unsafe { self.primary.grow(old_layout, new_layout, handle) }
+                {
+                    return Ok((FallbackStorageHandle::Primary(handle), size));
+                }
+
+                let (new_handle, size) = self.secondary.allocate(new_layout)?;
+                unsafe {
+                    self.secondary
+                        .resolve(new_handle)
+                        .cast::<u8>()
+                        .copy_from_nonoverlapping(
+                            self.primary.resolve(handle).cast(),
+                            old_layout.size(),
+                        );
+                    self.primary.deallocate(old_layout, handle);
+                }
+                Ok((FallbackStorageHandle::Secondary(new_handle), size))
+            }
+            FallbackStorageHandle::Secondary(handle) => {
+                let (handle, size) =
+                    unsafe { self.secondary.grow(old_layout, new_layout, handle)? };
+                Ok((FallbackStorageHandle::Secondary(handle), size))
+            }
+        }
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        match handle {
+            FallbackStorageHandle::Primary(handle) => {
+                let (handle, size) =
+                    unsafe { self.primary.shrink(old_layout, new_layout, handle)? };
+                Ok((FallbackStorageHandle::Primary(handle), size))
+            }
+            FallbackStorageHandle::Secondary(handle) => {
+                let (handle, size) =
+                    unsafe { self.secondary.shrink(old_layout, new_layout, handle)? };
+                Ok((FallbackStorageHandle::Secondary(handle), size))
+            }
+        }
+    }
+}
+
+unsafe impl<A: ShareableStorage, B: ShareableStorage> ShareableStorage for FallbackStorage<A, B> {
+    unsafe fn make_shared_copy(&self) -> Self {
+        unsafe {
+            Self {
+                primary: self.primary.make_shared_copy(),
+                secondary: self.secondary.make_shared_copy(),
+            }
+        }
+    }
+}
+
+unsafe impl<A: MultipleStorage, B: MultipleStorage> MultipleStorage for FallbackStorage<A, B> {}
+unsafe impl<A: StableStorage, B: StableStorage> StableStorage for FallbackStorage<A, B> {}
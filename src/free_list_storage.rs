@@ -0,0 +1,397 @@
+use crate::{
+    MultipleStorage, PointerStorage, StableStorage, Storage, StorageAllocError, StorageHandle,
+};
+use core::{alloc::Layout, cell::Cell, marker::PhantomData, mem::MaybeUninit, ptr::NonNull};
+
+/// The [`StorageHandle`] for [`FreeListStorage`],
+/// this is the offset of the allocation from the start of the buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FreeListStorageHandle {
+    offset: usize,
+}
+
+impl StorageHandle for FreeListStorageHandle {}
+
+/// How a [`FreeListStorage`] picks which free block to allocate from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FitStrategy {
+    /// Use the first free block that the allocation fits in
+    #[default]
+    FirstFit,
+    /// Use the smallest free block that the allocation fits in
+    BestFit,
+}
+
+/// Stored at the start of every free block, free blocks are kept in a list sorted by offset
+#[derive(Clone, Copy)]
+struct FreeBlock {
+    size: usize,
+    next: usize,
+}
+
+/// Stored directly before the start of every allocation
+#[derive(Clone, Copy)]
+struct AllocationHeader {
+    start: usize,
+    size: usize,
+}
+
+const NONE: usize = usize::MAX;
+const BLOCK_ALIGN: usize = align_of::<FreeBlock>();
+const MIN_BLOCK_SIZE: usize = size_of::<FreeBlock>();
+const HEADER_SIZE: usize = size_of::<AllocationHeader>();
+
+const fn align_up(value: usize, align: usize) -> Option<usize> {
+    match value.checked_add(align - 1) {
+        Some(value) => Some(value & !(align - 1)),
+        None => None,
+    }
+}
+
+/// A general purpose allocator that manages a fixed buffer using a list of free blocks
+///
+/// Adjacent free blocks are merged when deallocating, and allocations are grown in place when the block after them is free
+///
+/// ```
+/// use core::mem::MaybeUninit;
+/// use storage_api::{FreeListStorage, Vec, String};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let mut buffer = [MaybeUninit::uninit(); 256];
+/// let storage = FreeListStorage::from_uninit(&mut buffer);
+///
+/// let mut a = Vec::<u32, _>::new_in(&storage)?;
+/// let mut b = String::new_in(&storage)?;
+/// for i in 0..8 {
+///     a.push(i)?;
+///     b.push(char::from(b'a' + i as u8))?;
+/// }
+/// assert_eq!(&*a, &[0, 1, 2, 3, 4, 5, 6, 7]);
+/// assert_eq!(&*b, "abcdefgh");
+///
+/// drop((a, b));
+/// let big = Vec::<u8, _>::with_capacity_in(200, &storage)?; // all the space was returned
+/// assert!(big.capacity() >= 200);
+///
+/// # Ok(())
+/// # }
+/// ```
+pub struct FreeListStorage<'a> {
+    base: NonNull<u8>,
+    head: Cell<usize>,
+    strategy: FitStrategy,
+    _buffer: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+unsafe impl Send for FreeListStorage<'_> {}
+
+impl<'a> FreeListStorage<'a> {
+    /// Constructs a [`FreeListStorage`] that allocates from an uninitialised `buffer`
+    pub fn from_uninit(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        let start = NonNull::from(&mut *buffer).cast::<u8>();
+        let padding = start.align_offset(BLOCK_ALIGN).min(buffer.len());
+        let length = (buffer.len() - padding) & !(BLOCK_ALIGN - 1);
+
+        let this = Self {
+            base: unsafe { start.add(padding) },
+            head: Cell::new(NONE),
+            strategy: FitStrategy::FirstFit,
+            _buffer: PhantomData,
+        };
+        if length >= MIN_BLOCK_SIZE {
+            unsafe {
+                this.write_free(
+                    0,
+                    FreeBlock {
+                        size: length,
+                        next: NONE,
+                    },
+                )
+            };
+            this.head.set(0);
+        }
+        this
+    }
+
+    /// Returns the [`FitStrategy`] used when allocating
+    pub fn fit_strategy(&self) -> FitStrategy {
+        self.strategy
+    }
+
+    /// Sets the [`FitStrategy`] used when allocating
+    pub fn set_fit_strategy(&mut self, strategy: FitStrategy) {
+        self.strategy = strategy;
+    }
+
+    unsafe fn read_free(&self, offset: usize) -> FreeBlock {
+        unsafe { self.base.add(offset).cast::<FreeBlock>().read() }
+    }
+
+    unsafe fn write_free(&self, offset: usize, block: FreeBlock) {
+        unsafe { self.base.add(offset).cast::<FreeBlock>().write(block) }
+    }
+
+    unsafe fn header(&self, handle: FreeListStorageHandle) -> NonNull<AllocationHeader> {
+        unsafe {
+            self.base
+                .add(handle.offset - HEADER_SIZE)
+                .cast::<AllocationHeader>()
+        }
+    }
+
+    /// Points the free block at `previous` (or the head of the list if `previous` is `NONE`) at `offset`
+    unsafe fn link(&self, previous: usize, offset: usize) {
+        if previous == NONE {
+            self.head.set(offset);
+        } else {
+            unsafe {
+                let mut block = self.read_free(previous);
+                block.next = offset;
+                self.write_free(previous, block);
+            }
+        }
+    }
+
+    /// Returns the offset that an allocation with `layout` would start at if it was placed at `offset`
+    fn data_offset(&self, offset: usize, layout: Layout) -> Option<usize> {
+        let base = self.base.addr().get();
+        let align = layout.align().max(BLOCK_ALIGN);
+        Some(align_up(base.checked_add(offset)?.checked_add(HEADER_SIZE)?, align)? - base)
+    }
+
+    /// Finds a free block that `layout` fits in, returns the block before it, the block, and where the allocation starts
+    fn find(&self, layout: Layout) -> Option<(usize, usize, FreeBlock, usize)> {
+        let mut found: Option<(usize, usize, FreeBlock, usize)> = None;
+        let mut previous = NONE;
+        let mut offset = self.head.get();
+        while offset != NONE {
+            let block = unsafe { self.read_free(offset) };
+            if let Some(data) = self.data_offset(offset, layout)
+                && let Some(end) = data.checked_add(layout.size())
+                && end <= offset + block.size
+                && found.is_none_or(|(_, _, found, _)| block.size < found.size)
+            {
+                found = Some((previous, offset, block, data));
+                if self.strategy == FitStrategy::FirstFit {
+                    break;
+                }
+            }
+            previous = offset;
+            offset = block.next;
+        }
+        found
+    }
+
+    /// Returns the range `offset..offset + size` to the list of free blocks, merging it with its neighbours
+    unsafe fn free(&self, offset: usize, size: usize) {
+        let mut previous = NONE;
+        let mut next = self.head.get();
+        while next != NONE && next < offset {
+            previous = next;
+            next = unsafe { self.read_free(next).next };
+        }
+
+        unsafe {
+            let previous_block = (previous != NONE).then(|| self.read_free(previous));
+            let (offset, mut block) = if let Some(previous_block) = previous_block
+                && previous + previous_block.size == offset
+            {
+                (
+                    previous,
+                    FreeBlock {
+                        size: previous_block.size + size,
+                        next,
+                    },
+                )
+            } else {
+                self.link(previous, offset);
+                (offset, FreeBlock { size, next })
+            };
+
+            if next != NONE && offset + block.size == next {
+                let next_block = self.read_free(next);
+                block.size += next_block.size;
+                block.next = next_block.next;
+            }
+            self.write_free(offset, block);
+        }
+    }
+}
+
+unsafe impl Storage for FreeListStorage<'_> {
+    type Handle = FreeListStorageHandle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { self.base.add(handle.offset).cast() }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let (previous, offset, block, data) = self.find(layout).ok_or(StorageAllocError)?;
+        let block_end = offset + block.size;
+        let end = align_up(data + layout.size(), BLOCK_ALIGN)
+            .unwrap_or(block_end)
+            .min(block_end);
+
+        unsafe {
+            let mut previous = previous;
+            let mut start = offset;
+            if data - HEADER_SIZE - offset >= MIN_BLOCK_SIZE {
+                start = data - HEADER_SIZE;
+                self.write_free(
+                    offset,
+                    FreeBlock {
+                        size: start - offset,
+                        next: block.next,
+                    },
+                );
+                previous = offset;
+            } else {
+                self.link(previous, block.next);
+            }
+
+            let mut end = end;
+            if block_end - end >= MIN_BLOCK_SIZE {
+                self.write_free(
+                    end,
+                    FreeBlock {
+                        size: block_end - end,
+                        next: block.next,
+                    },
+                );
+                self.link(previous, end);
+            } else {
+                end = block_end;
+            }
+
+            let handle = FreeListStorageHandle { offset: data };
+            self.header(handle).write(AllocationHeader {
+                start,
+                size: end - start,
+            });
+            Ok((handle, end - data))
+        }
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        _ = layout;
+        unsafe {
+            let AllocationHeader { start, size } = self.header(handle).read();
+            self.free(start, size);
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let data = handle.offset;
+        let header = unsafe { self.header(handle).read() };
+        let block_end = header.start + header.size;
+
+        if self.base.addr().get().wrapping_add(data) % new_layout.align() == 0 {
+            let end = data
+                .checked_add(new_layout.size())
+                .and_then(|end| align_up(end, BLOCK_ALIGN))
+                .ok_or(StorageAllocError)?;
+            if end <= block_end {
+                return Ok((handle, block_end - data));
+            }
+
+            let mut previous = NONE;
+            let mut next = self.head.get();
+            while next != NONE && next < block_end {
+                previous = next;
+                next = unsafe { self.read_free(next).next };
+            }
+
+            if next == block_end {
+                let next_block = unsafe { self.read_free(next) };
+                let next_end = next + next_block.size;
+                if end <= next_end {
+                    let new_end = if next_end - end >= MIN_BLOCK_SIZE {
+                        unsafe {
+                            self.write_free(
+                                end,
+                                FreeBlock {
+                                    size: next_end - end,
+                                    next: next_block.next,
+                                },
+                            );
+                            self.link(previous, end);
+                        }
+                        end
+                    } else {
+                        unsafe { self.link(previous, next_block.next) };
+                        next_end
+                    };
+
+                    unsafe {
+                        self.header(handle).write(AllocationHeader {
+                            start: header.start,
+                            size: new_end - header.start,
+                        });
+                    }
+                    return Ok((handle, new_end - data));
+                }
+            }
+        }
+
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            self.resolve(new_handle)
+                .cast::<u8>()
+                .copy_from_nonoverlapping(self.resolve(handle).cast(), old_layout.size());
+            self.deallocate(old_layout, handle);
+        }
+        Ok((new_handle, new_size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let data = handle.offset;
+        if self.base.addr().get().wrapping_add(data) % new_layout.align() != 0 {
+            let (new_handle, new_size) = self.allocate(new_layout)?;
+            unsafe {
+                self.resolve(new_handle)
+                    .cast::<u8>()
+                    .copy_from_nonoverlapping(self.resolve(handle).cast(), new_layout.size());
+                self.deallocate(old_layout, handle);
+            }
+            return Ok((new_handle, new_size));
+        }
+
+        unsafe {
+            let header = self.header(handle).read();
+            let block_end = header.start + header.size;
+            let end = align_up(data + new_layout.size(), BLOCK_ALIGN).unwrap_or(block_end);
+            if block_end - end < MIN_BLOCK_SIZE {
+                return Ok((handle, block_end - data));
+            }
+
+            self.header(handle).write(AllocationHeader {
+                start: header.start,
+                size: end - header.start,
+            });
+            self.free(end, block_end - end);
+            Ok((handle, end - data))
+        }
+    }
+}
+
+unsafe impl MultipleStorage for FreeListStorage<'_> {}
+unsafe impl StableStorage for FreeListStorage<'_> {}
+unsafe impl PointerStorage for FreeListStorage<'_> {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        FreeListStorageHandle {
+            offset: ptr.addr().get() - self.base.addr().get(),
+        }
+    }
+}
@@ -1,7 +1,8 @@
 extern crate alloc;
 
 use crate::{
-    MultipleStorage, ShareableStorage, StableStorage, Storage, StorageAllocError, StorageHandle,
+    MultipleStorage, PointerStorage, ShareableStorage, StableStorage, Storage, StorageAllocError,
+    StorageHandle,
 };
 use core::{alloc::Layout, ptr::NonNull};
 
@@ -121,3 +122,8 @@ unsafe impl ShareableStorage for Global {
 
 unsafe impl MultipleStorage for Global {}
 unsafe impl StableStorage for Global {}
+unsafe impl PointerStorage for Global {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        GlobalHandle(ptr)
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{
+    MultipleStorage, PointerStorage, ShareableStorage, StableStorage, Storage, StorageAllocError,
+    StorageHandle, os::page_size,
+};
+use core::{alloc::Layout, ptr::NonNull};
+
+/// The [`StorageHandle`] for [`GuardPageStorage`],
+/// this is a wrapper around a [`NonNull<()>`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GuardPageStorageHandle(pub NonNull<()>);
+
+unsafe impl Send for GuardPageStorageHandle {}
+unsafe impl Sync for GuardPageStorageHandle {}
+
+impl StorageHandle for GuardPageStorageHandle {}
+
+/// A [`Storage`] that puts every allocation in its own `mmap`ed pages, right before a page that cant be accessed,
+/// so reading or writing past the end of an allocation through a pointer from [`Storage::resolve`] segfaults immediately
+///
+/// If [`GuardPageStorage::protect_freed`] is set then freed allocations are made inaccessible instead of unmapped,
+/// so using them after they are freed segfaults too, but the address space they use is never reused
+///
+/// Every allocation uses at least 2 pages, and growing or shrinking always moves the allocation so old pointers to it stop working,
+/// allocations with an alignment greater than the page size fail
+///
+/// ```
+/// use storage_api::{GuardPageStorage, Vec};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let mut v = Vec::<u64, _>::with_capacity_in(3, GuardPageStorage::new())?;
+/// v.extend_from_slice(&[1, 2, 3])?;
+/// assert_eq!(&*v, &[1, 2, 3]);
+///
+/// let end = unsafe { v.as_mut_ptr().add(v.capacity()) };
+/// // `end` is in the guard page, so `unsafe { end.write(4) }` would segfault
+///
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GuardPageStorage {
+    protect_freed: bool,
+}
+
+impl GuardPageStorage {
+    /// Constructs a new [`GuardPageStorage`] that unmaps freed allocations
+    pub const fn new() -> Self {
+        Self {
+            protect_freed: false,
+        }
+    }
+
+    /// Returns whether freed allocations are made inaccessible instead of being unmapped
+    pub fn protect_freed(&self) -> bool {
+        self.protect_freed
+    }
+
+    /// Sets whether freed allocations are made inaccessible instead of being unmapped
+    pub fn set_protect_freed(&mut self, protect_freed: bool) {
+        self.protect_freed = protect_freed;
+    }
+
+    /// Returns the start of the pages for the allocation at `ptr`, and their length not including the guard page
+    fn pages(ptr: NonNull<()>, layout: Layout) -> (NonNull<()>, usize) {
+        let page = page_size();
+        let length = layout.size().next_multiple_of(page);
+        let guard = (ptr.addr().get() + layout.size()).next_multiple_of(page);
+        (
+            ptr.with_addr(unsafe { core::num::NonZero::new_unchecked(guard - length) }),
+            length,
+        )
+    }
+}
+
+unsafe impl Storage for GuardPageStorage {
+    type Handle = GuardPageStorageHandle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        handle.0
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let page = page_size();
+        if layout.align() > page {
+            return Err(StorageAllocError);
+        }
+        let length = layout
+            .size()
+            .checked_next_multiple_of(page)
+            .ok_or(StorageAllocError)?;
+
+        unsafe {
+            let start = libc::mmap(
+                core::ptr::null_mut(),
+                length.checked_add(page).ok_or(StorageAllocError)?,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            if start == libc::MAP_FAILED {
+                return Err(StorageAllocError);
+            }
+
+            let guard = start.byte_add(length);
+            if libc::mprotect(guard, page, libc::PROT_NONE) != 0 {
+                libc::munmap(start, length + page);
+                return Err(StorageAllocError);
+            }
+
+            let ptr = guard.byte_sub(layout.size());
+            let ptr = ptr.byte_sub(ptr.addr() % layout.align());
+            Ok((
+                GuardPageStorageHandle(NonNull::new_unchecked(ptr.cast())),
+                layout.size(),
+            ))
+        }
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        let (start, length) = Self::pages(handle.0, layout);
+        let length = length + page_size();
+        unsafe {
+            if self.protect_freed {
+                libc::mprotect(start.as_ptr().cast(), length, libc::PROT_NONE);
+            } else {
+                libc::munmap(start.as_ptr().cast(), length);
+            }
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            new_handle
+                .0
+                .cast::<u8>()
+                .copy_from_nonoverlapping(handle.0.cast(), old_layout.size());
+            self.deallocate(old_layout, handle);
+        }
+        Ok((new_handle, new_size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            new_handle
+                .0
+                .cast::<u8>()
+                .copy_from_nonoverlapping(handle.0.cast(), new_layout.size());
+            self.deallocate(old_layout, handle);
+        }
+        Ok((new_handle, new_size))
+    }
+}
+
+unsafe impl ShareableStorage for GuardPageStorage {
+    unsafe fn make_shared_copy(&self) -> Self {
+        *self
+    }
+}
+
+unsafe impl MultipleStorage for GuardPageStorage {}
+unsafe impl StableStorage for GuardPageStorage {}
+unsafe impl PointerStorage for GuardPageStorage {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        GuardPageStorageHandle(ptr)
+    }
+}
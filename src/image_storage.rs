@@ -0,0 +1,342 @@
+use crate::{
+    MultipleStorage, PointerStorage, StableStorage, Storage, StorageAllocError, StorageHandle,
+    size_class_heap::{HeapHeader, SizeClassHeap},
+};
+use core::{alloc::Layout, marker::PhantomData, mem::MaybeUninit, ptr::NonNull};
+
+/// The [`StorageHandle`] for [`ImageStorage`],
+/// this is the offset of the allocation from the start of the image, so it stays valid when the image is copied somewhere else
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ImageStorageHandle {
+    /// The offset of the allocation from the start of the image
+    pub offset: usize,
+}
+
+impl StorageHandle for ImageStorageHandle {}
+
+/// The error returned when an [`ImageStorage`] cant be created or opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageError {
+    /// The buffer isnt aligned to [`ImageStorage::ALIGN`]
+    Misaligned,
+    /// The buffer is too small to hold the image
+    TooSmall,
+    /// The buffer doesnt start with the magic bytes of an image
+    NotAnImage,
+    /// The image was made by an unsupported version, or on a target with a different byte order or pointer size
+    Incompatible,
+    /// The state of the allocator or the root handle in the image is invalid
+    Corrupted,
+}
+
+/// Stored at the start of the image
+#[repr(C)]
+struct ImageHeader {
+    magic: [u8; 8],
+    version: u32,
+    /// `BYTE_ORDER` in the byte order of the target that made the image
+    byte_order: u32,
+    /// `size_of::<usize>()` on the target that made the image
+    pointer_size: u64,
+    /// the offset of the allocation set with [`ImageStorage::set_root`], or 0
+    root: u64,
+    heap: HeapHeader,
+}
+
+const _: () = assert!(size_of::<ImageHeader>() == 552);
+
+const MAGIC: [u8; 8] = *b"STORIMG\0";
+const VERSION: u32 = 1;
+const BYTE_ORDER: u32 = 0x01020304;
+
+/// A [`Storage`] that keeps all of its state inside its buffer, so the buffer is an image that can be saved and later reopened in place with [`ImageStorage::open`],
+/// without changing any of the bytes in it
+///
+/// Handles are offsets from the start of the image, so collections can be stored in an image by storing the parts from [`Vec::into_raw_parts`](crate::Vec::into_raw_parts),
+/// [`String::into_raw_parts`](crate::String::into_raw_parts), or [`Box::into_raw_parts`](crate::Box::into_raw_parts),
+/// and reconstructed with `from_raw_parts` after the image is reopened.
+/// Collections themselves cant be stored in an image because they contain a reference to their [`Storage`],
+/// so data structures like trees should link to each other with [`ImageStorageHandle`]s
+///
+/// [`ImageStorage::set_root`] stores a handle in the header of the image, so the data can be found again after reopening it,
+/// and [`ImageStorage::contains`] can be used to check handles read from the image before using them
+///
+/// # Image format
+/// All integers are in the byte order of the target that made the image, and the image must start at an address aligned to [`ImageStorage::ALIGN`]
+///
+/// | Offset | Size | Contents |
+/// |---|---|---|
+/// | 0 | 8 | The magic bytes `STORIMG\0` |
+/// | 8 | 4 | The version of the format, `1` |
+/// | 12 | 4 | `0x01020304`, to detect images from targets with a different byte order |
+/// | 16 | 8 | `size_of::<usize>()` of the target that made the image |
+/// | 24 | 8 | The offset of the root allocation, or 0 if there isnt one |
+/// | 32 | 8 | The length of the image, allocations are never made past this |
+/// | 40 | 512 | The offset of the first free block of each size `2^i`, or 0 if there isnt one |
+/// | 552 | | Blocks |
+///
+/// Blocks are a power of 2 in size and aligned to their size (up to [`ImageStorage::ALIGN`]),
+/// the first 8 bytes of a free block are the offset of the next free block of the same size, or 0.
+/// An allocation starts 16 bytes or its alignment (whichever is greater) after the start of its block,
+/// and the 16 bytes before it are the offset of the start of its block followed by `i` where `2^i` is the size of the block
+///
+/// ```
+/// use core::{alloc::Layout, mem::MaybeUninit};
+/// use storage_api::{Box, ImageStorage, String, storages::ImageStorageHandle};
+///
+/// #[repr(C, align(16))]
+/// struct Buffer([MaybeUninit<u8>; 4096]);
+///
+/// // this cant have any padding bytes, so it doesnt use `Option<ImageStorageHandle>`
+/// struct Node {
+///     name: (ImageStorageHandle, usize, usize),
+///     /// the offset of the next node, or 0
+///     next: usize,
+/// }
+///
+/// let mut buffer = Buffer([MaybeUninit::uninit(); 4096]);
+/// let mut storage = ImageStorage::new(&mut buffer.0).unwrap();
+///
+/// let mut next = 0;
+/// for name in ["c", "b", "a"] {
+///     let (_, handle, length, capacity) = String::from_str_in(name, &storage).unwrap().into_raw_parts();
+///     let node = Box::new_in(Node { name: (handle, length, capacity), next }, &storage).unwrap();
+///     next = Box::into_raw_parts(node).1.offset;
+/// }
+/// storage.set_root(Some(ImageStorageHandle { offset: next }));
+///
+/// // the image could be written to a file here and read back later
+/// let image = unsafe { storage.image() }.to_vec();
+/// let mut buffer = Buffer([MaybeUninit::uninit(); 4096]);
+/// buffer.0[..image.len()].write_copy_of_slice(&image);
+/// let storage = unsafe { ImageStorage::open(&mut buffer.0) }.unwrap();
+///
+/// let mut names = std::vec::Vec::new();
+/// let mut next = storage.root().unwrap().offset;
+/// while next != 0 {
+///     let handle = ImageStorageHandle { offset: next };
+///     assert!(storage.contains(handle, Layout::new::<Node>()));
+///     let node = unsafe { Box::<Node, _>::from_raw_parts(&storage, handle, ()) };
+///     let (handle, length, capacity) = node.name;
+///     assert!(storage.contains(handle, Layout::array::<u8>(capacity).unwrap()));
+///     names.push(unsafe { String::from_raw_parts(&storage, handle, length, capacity) });
+///     next = node.next;
+/// }
+/// assert!(names.iter().map(|name| &**name).eq(["a", "b", "c"]));
+/// ```
+pub struct ImageStorage<'a> {
+    base: NonNull<u8>,
+    length: usize,
+    _buffer: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+unsafe impl Send for ImageStorage<'_> {}
+
+impl<'a> ImageStorage<'a> {
+    /// The alignment that the start of the image must have, allocations with a greater alignment fail
+    pub const ALIGN: usize = 16;
+
+    /// Constructs an [`ImageStorage`] that creates a new empty image in an uninitialised `buffer`
+    pub fn new(buffer: &'a mut [MaybeUninit<u8>]) -> Result<Self, ImageError> {
+        let this = Self::from_buffer(buffer)?;
+        unsafe {
+            this.header().write(ImageHeader {
+                magic: MAGIC,
+                version: VERSION,
+                byte_order: BYTE_ORDER,
+                pointer_size: size_of::<usize>() as u64,
+                root: 0,
+                heap: HeapHeader::new(size_of::<ImageHeader>()),
+            });
+        }
+        Ok(this)
+    }
+
+    /// Constructs an [`ImageStorage`] that opens the image at the start of `buffer`, all allocations in the image stay valid
+    ///
+    /// The header of the image and the free blocks are validated, so the image doesnt need to be trusted,
+    /// and the rest of `buffer` after the image can be used for new allocations
+    ///
+    /// # Safety
+    /// The bytes of the image at the start of `buffer` must be initialised, like by copying the bytes from [`ImageStorage::image`] into it
+    pub unsafe fn open(buffer: &'a mut [MaybeUninit<u8>]) -> Result<Self, ImageError> {
+        let this = Self::from_buffer(buffer)?;
+        let header = unsafe { this.header().as_ref() };
+        if header.magic != MAGIC {
+            return Err(ImageError::NotAnImage);
+        }
+        if header.version != VERSION
+            || header.byte_order != BYTE_ORDER
+            || header.pointer_size != size_of::<usize>() as u64
+        {
+            return Err(ImageError::Incompatible);
+        }
+        if header.heap.top() > this.length {
+            return Err(ImageError::TooSmall);
+        }
+        if !this.heap().validate(size_of::<ImageHeader>(), this.length)
+            || this
+                .root()
+                .is_some_and(|root| !this.contains(root, Layout::new::<()>()))
+        {
+            return Err(ImageError::Corrupted);
+        }
+        Ok(this)
+    }
+
+    fn from_buffer(buffer: &'a mut [MaybeUninit<u8>]) -> Result<Self, ImageError> {
+        if !buffer.as_ptr().addr().is_multiple_of(Self::ALIGN) {
+            return Err(ImageError::Misaligned);
+        }
+        if buffer.len() < size_of::<ImageHeader>() {
+            return Err(ImageError::TooSmall);
+        }
+        Ok(Self {
+            base: NonNull::from(&mut *buffer).cast(),
+            length: buffer.len(),
+            _buffer: PhantomData,
+        })
+    }
+
+    /// Returns the bytes of the image, which can be saved and reopened with [`ImageStorage::open`]
+    ///
+    /// This is only the part of the buffer that has been used
+    ///
+    /// Bytes in the image that havent been written to are zero
+    ///
+    /// # Safety
+    /// Every value written to allocations in the image must have no uninitialised bytes, this means that values with padding bytes cant be stored in the image
+    pub unsafe fn image(&mut self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.base.as_ptr(), self.image_len()) }
+    }
+
+    /// Returns the length of the image in bytes
+    pub fn image_len(&self) -> usize {
+        unsafe { self.header().as_ref().heap.top() }
+    }
+
+    /// Returns the length of the buffer in bytes
+    pub fn capacity(&self) -> usize {
+        self.length
+    }
+
+    /// Returns the handle stored with [`ImageStorage::set_root`]
+    pub fn root(&self) -> Option<ImageStorageHandle> {
+        let root = unsafe { (*self.header().as_ptr()).root };
+        (root != 0).then_some(ImageStorageHandle {
+            offset: root as usize,
+        })
+    }
+
+    /// Stores `root` in the header of the image, so it can be retrieved with [`ImageStorage::root`] after reopening the image
+    pub fn set_root(&self, root: Option<ImageStorageHandle>) {
+        let root = root.map_or(0, |root| root.offset as u64);
+        unsafe { (*self.header().as_ptr()).root = root };
+    }
+
+    /// Returns whether `handle` looks like an allocation in the image that fits `layout`
+    ///
+    /// This cant detect every invalid handle (like one to an allocation that has been freed and reused),
+    /// but if it returns `true` then resolving `handle` gives a pointer to `layout.size()` bytes inside the image that are aligned to `layout.align()`
+    /// and arent part of a free block
+    pub fn contains(&self, handle: ImageStorageHandle, layout: Layout) -> bool {
+        self.heap()
+            .contains(size_of::<ImageHeader>(), handle.offset, layout)
+    }
+
+    /// Returns the buffer that this [`ImageStorage`] was allocating from
+    ///
+    /// Any data written by allocations is left in the buffer
+    pub fn into_inner(self) -> &'a mut [MaybeUninit<u8>] {
+        unsafe { core::slice::from_raw_parts_mut(self.base.as_ptr().cast(), self.length) }
+    }
+
+    fn header(&self) -> NonNull<ImageHeader> {
+        self.base.cast()
+    }
+
+    fn heap(&self) -> SizeClassHeap {
+        unsafe {
+            SizeClassHeap::new(
+                self.base,
+                NonNull::new_unchecked(&raw mut (*self.header().as_ptr()).heap),
+                Self::ALIGN,
+            )
+        }
+    }
+}
+
+unsafe impl Storage for ImageStorage<'_> {
+    type Handle = ImageStorageHandle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { self.base.add(handle.offset).cast() }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let (offset, size) = self.heap().allocate(layout, |range| {
+            if range.end > self.length {
+                return Err(StorageAllocError);
+            }
+            // the padding and the unused parts of the block become part of the image, so they must be initialised
+            unsafe { self.base.add(range.start).write_bytes(0, range.len()) };
+            Ok(())
+        })?;
+        Ok((ImageStorageHandle { offset }, size))
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        _ = layout;
+        unsafe { self.heap().deallocate(handle.offset) }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        if let Some(size) = unsafe { self.heap().resize_in_place(handle.offset, new_layout) } {
+            return Ok((handle, size));
+        }
+
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            self.resolve(new_handle)
+                .cast::<u8>()
+                .copy_from_nonoverlapping(self.resolve(handle).cast(), old_layout.size());
+            self.deallocate(old_layout, handle);
+        }
+        Ok((new_handle, new_size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        if let Some(size) = unsafe { self.heap().resize_in_place(handle.offset, new_layout) } {
+            return Ok((handle, size));
+        }
+
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            self.resolve(new_handle)
+                .cast::<u8>()
+                .copy_from_nonoverlapping(self.resolve(handle).cast(), new_layout.size());
+            self.deallocate(old_layout, handle);
+        }
+        Ok((new_handle, new_size))
+    }
+}
+
+unsafe impl MultipleStorage for ImageStorage<'_> {}
+unsafe impl StableStorage for ImageStorage<'_> {}
+unsafe impl PointerStorage for ImageStorage<'_> {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        ImageStorageHandle {
+            offset: ptr.addr().get() - self.base.addr().get(),
+        }
+    }
+}
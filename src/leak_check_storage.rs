@@ -0,0 +1,232 @@
+extern crate alloc;
+
+use crate::{MultipleStorage, StableStorage, Storage, StorageAllocError};
+use alloc::collections::BTreeMap;
+use cfg_if::cfg_if;
+use core::{alloc::Layout, cell::RefCell, ptr::NonNull};
+
+/// An allocation that a [`LeakCheckStorage`] is keeping track of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct LiveAllocation {
+    layout: Layout,
+    size: usize,
+}
+
+/// A wrapper around a [`Storage`] that keeps track of every live allocation,
+/// so that allocations that are never deallocated can be reported
+///
+/// This also panics when [`Storage::deallocate`], [`Storage::grow`] or [`Storage::shrink`] are given a handle that isnt live,
+/// or a [`Layout`] that doesnt fit the allocation
+/// (it must have the same alignment, and a size between the requested size and the size returned by the inner [`Storage`])
+///
+/// Dropping a [`LeakCheckStorage`] calls [`LeakCheckStorage::assert_no_leaks`], unless disabled with [`LeakCheckStorage::set_check_on_drop`].
+/// With the `std` feature it is skipped if the thread is already panicking, because panicking again would abort and hide the first panic
+///
+/// ```
+/// use storage_api::{Global, LeakCheckStorage, Vec};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let storage = LeakCheckStorage::new(Global);
+/// let mut v = Vec::<i32, _>::new_in(&storage)?;
+/// v.extend_from_slice(&[1, 2, 3])?;
+///
+/// let (s, handle, length, capacity) = v.into_raw_parts();
+/// assert_eq!(storage.live_allocations(), 1);
+///
+/// drop(unsafe { Vec::<i32, _>::from_raw_parts(s, handle, length, capacity) });
+/// storage.assert_no_leaks();
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Forgetting to deallocate makes it panic when dropped
+/// ```should_panic
+/// use storage_api::{Global, LeakCheckStorage, Vec};
+///
+/// let storage = LeakCheckStorage::new(Global);
+/// let mut v = Vec::<i32, _>::new_in(&storage).unwrap();
+/// v.push(1).unwrap();
+/// core::mem::forget(v);
+/// ```
+pub struct LeakCheckStorage<S: Storage> {
+    storage: S,
+    live: RefCell<BTreeMap<S::Handle, LiveAllocation>>,
+    check_on_drop: bool,
+}
+
+impl<S: Storage> LeakCheckStorage<S> {
+    /// Constructs a new [`LeakCheckStorage`] wrapping `storage`
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage,
+            live: RefCell::new(BTreeMap::new()),
+            check_on_drop: true,
+        }
+    }
+
+    /// Returns the number of allocations that havent been deallocated yet
+    ///
+    /// Allocations that the inner [`Storage`] returned 0 bytes for are not counted
+    pub fn live_allocations(&self) -> usize {
+        self.live.borrow().len()
+    }
+
+    /// Panics if there are any allocations that havent been deallocated yet, listing their handles and [`Layout`]s
+    #[track_caller]
+    pub fn assert_no_leaks(&self) {
+        let live = self.live.borrow();
+        if !live.is_empty() {
+            panic!(
+                "{} allocation(s) were never deallocated: {:?}",
+                live.len(),
+                live.iter()
+                    .map(|(handle, allocation)| (handle, allocation.layout))
+                    .collect::<alloc::vec::Vec<_>>(),
+            );
+        }
+    }
+
+    /// Returns whether dropping the [`LeakCheckStorage`] calls [`LeakCheckStorage::assert_no_leaks`]
+    pub fn check_on_drop(&self) -> bool {
+        self.check_on_drop
+    }
+
+    /// Sets whether dropping the [`LeakCheckStorage`] calls [`LeakCheckStorage::assert_no_leaks`]
+    pub fn set_check_on_drop(&mut self, check_on_drop: bool) {
+        self.check_on_drop = check_on_drop;
+    }
+
+    /// Returns a reference to the inner [`Storage`]
+    pub fn inner(&self) -> &S {
+        &self.storage
+    }
+
+    fn insert(&self, handle: S::Handle, layout: Layout, size: usize) {
+        if size != 0 {
+            self.live
+                .borrow_mut()
+                .insert(handle, LiveAllocation { layout, size });
+        }
+    }
+
+    #[track_caller]
+    fn remove(&self, handle: S::Handle, layout: Layout) -> Option<LiveAllocation> {
+        let mut live = self.live.borrow_mut();
+        let Some(allocation) = live.get(&handle) else {
+            // the inner `Storage` returned 0 bytes, so it was never tracked
+            if layout.size() == 0 {
+                return None;
+            }
+            drop(live);
+            panic!("{handle:?} was used with {layout:?} but it is not a live allocation");
+        };
+
+        let allocation = *allocation;
+        if layout.align() != allocation.layout.align()
+            || layout.size() < allocation.layout.size()
+            || layout.size() > allocation.size
+        {
+            drop(live);
+            panic!(
+                "{handle:?} was used with {layout:?} but it was allocated with {:?} and a size of {}",
+                allocation.layout, allocation.size,
+            );
+        }
+        live.remove(&handle)
+    }
+
+    fn restore(&self, handle: S::Handle, allocation: Option<LiveAllocation>) {
+        if let Some(allocation) = allocation {
+            self.live.borrow_mut().insert(handle, allocation);
+        }
+    }
+}
+
+unsafe impl<S: Storage> Storage for LeakCheckStorage<S> {
+    type Handle = S::Handle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { self.storage.resolve(handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let (handle, size) = self.storage.allocate(layout)?;
+        self.insert(handle, layout, size);
+        Ok((handle, size))
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        self.remove(handle, layout);
+        unsafe { self.storage.deallocate(layout, handle) }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let allocation = self.remove(handle, old_layout);
+        match unsafe { self.storage.grow(old_layout, new_layout, handle) } {
+            Ok((new_handle, size)) => {
+                self.insert(new_handle, new_layout, size);
+                Ok((new_handle, size))
+            }
+            Err(error) => {
+                self.restore(handle, allocation);
+                Err(error)
+            }
+        }
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let allocation = self.remove(handle, old_layout);
+        match unsafe { self.storage.shrink(old_layout, new_layout, handle) } {
+            Ok((new_handle, size)) => {
+                self.insert(new_handle, new_layout, size);
+                Ok((new_handle, size))
+            }
+            Err(error) => {
+                self.restore(handle, allocation);
+                Err(error)
+            }
+        }
+    }
+}
+
+unsafe impl<S: MultipleStorage> MultipleStorage for LeakCheckStorage<S> {}
+unsafe impl<S: StableStorage> StableStorage for LeakCheckStorage<S> {}
+
+impl<S: Storage + Default> Default for LeakCheckStorage<S> {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<S: Storage> Drop for LeakCheckStorage<S> {
+    fn drop(&mut self) {
+        if self.check_on_drop && !panicking() {
+            self.assert_no_leaks();
+        }
+    }
+}
+
+/// Returns whether the thread is unwinding from a panic, this is always false without the `std` feature
+fn panicking() -> bool {
+    cfg_if! {
+        if #[cfg(feature = "std")] {
+            extern crate std;
+            std::thread::panicking()
+        } else {
+            false
+        }
+    }
+}
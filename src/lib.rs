@@ -18,28 +18,117 @@
     )
 )]
 
+#[cfg(feature = "nightly")]
+pub use allocator_storage::AllocatorStorage;
+pub use buddy_storage::BuddyStorage;
+pub use bump_storage::BumpStorage;
+pub use checked_storage::CheckedStorage;
+pub use compacting_storage::CompactingStorage;
+pub use concurrent_bump_storage::ConcurrentBumpStorage;
+pub use debug_storage::DebugStorage;
+pub use failing_storage::FailingStorage;
+pub use fallback_storage::FallbackStorage;
+pub use free_list_storage::FreeListStorage;
 pub use global_storage::Global;
+#[cfg(all(feature = "unix", target_os = "linux"))]
+pub use guard_page_storage::GuardPageStorage;
+pub use image_storage::ImageStorage;
 pub use inline_storage::InlineStorage;
+pub use leak_check_storage::LeakCheckStorage;
+pub use limit_storage::LimitStorage;
+pub use locked_storage::LockedStorage;
+#[cfg(all(feature = "unix", target_os = "linux"))]
+pub use mmap_file_storage::MmapFileStorage;
+pub use pool_storage::{HeapPoolStorage, PoolStorage};
+pub use segregator_storage::SegregatorStorage;
 pub use sharable_storage_wrapper::ShareableStorageWrapper;
+#[cfg(all(feature = "unix", target_os = "linux"))]
+pub use shared_memory_storage::SharedMemoryStorage;
 pub use slot_storage::SlotStorage;
+pub use small_storage::SmallStorage;
+pub use stats_storage::StatsStorage;
+pub use storage_allocator::StorageAllocator;
 pub use storage_box::Box;
 pub use storage_string::String;
 pub use storage_vec::Vec;
+pub use tlsf_storage::TlsfStorage;
+pub use tracing_storage::TracingStorage;
 
+#[cfg(feature = "nightly")]
+mod allocator_storage;
+mod buddy_storage;
+mod bump_storage;
+mod checked_storage;
+mod compacting_storage;
+mod concurrent_bump_storage;
+mod debug_storage;
+mod failing_storage;
+mod fallback_storage;
+mod free_list_storage;
 mod global_storage;
+#[cfg(all(feature = "unix", target_os = "linux"))]
+mod guard_page_storage;
+mod image_storage;
 mod inline_storage;
+mod leak_check_storage;
+mod limit_storage;
+mod locked_storage;
+#[cfg(all(feature = "unix", target_os = "linux"))]
+mod mmap_file_storage;
+#[cfg(all(feature = "unix", target_os = "linux"))]
+mod os;
+mod pool_storage;
+mod segregator_storage;
 mod sharable_storage_wrapper;
+#[cfg(all(feature = "unix", target_os = "linux"))]
+mod shared_memory_storage;
+mod size_class_heap;
 mod slot_storage;
+mod small_storage;
+mod stats_storage;
+mod storage_allocator;
 mod storage_box;
 mod storage_string;
 mod storage_vec;
+mod tlsf_storage;
+mod tracing_storage;
 
 /// The types that implement [`Storage`]
 pub mod storages {
+    #[cfg(feature = "nightly")]
+    pub use crate::allocator_storage::{AllocatorStorage, AllocatorStorageHandle};
+    pub use crate::buddy_storage::{BuddyStorage, BuddyStorageHandle};
+    pub use crate::bump_storage::{BumpStorage, BumpStorageHandle};
+    pub use crate::checked_storage::{CheckedStorage, CheckedStorageHandle};
+    pub use crate::compacting_storage::{CompactingStorage, CompactingStorageHandle};
+    pub use crate::concurrent_bump_storage::{ConcurrentBumpStorage, ConcurrentBumpStorageHandle};
+    pub use crate::debug_storage::{DebugStorage, DebugStorageHandle};
+    pub use crate::failing_storage::{
+        FailEveryNth, FailLargerThan, FailNever, FailNth, FailRandomly, FailingStorage,
+        FailurePolicy,
+    };
+    pub use crate::fallback_storage::{FallbackStorage, FallbackStorageHandle};
+    pub use crate::free_list_storage::{FitStrategy, FreeListStorage, FreeListStorageHandle};
     pub use crate::global_storage::{Global, GlobalHandle};
+    #[cfg(all(feature = "unix", target_os = "linux"))]
+    pub use crate::guard_page_storage::{GuardPageStorage, GuardPageStorageHandle};
+    pub use crate::image_storage::{ImageError, ImageStorage, ImageStorageHandle};
     pub use crate::inline_storage::{InlineStorage, InlineStorageHandle};
+    pub use crate::leak_check_storage::LeakCheckStorage;
+    pub use crate::limit_storage::LimitStorage;
+    pub use crate::locked_storage::{LockedStorage, RawLock, SpinLock};
+    #[cfg(all(feature = "unix", target_os = "linux"))]
+    pub use crate::mmap_file_storage::{MmapFileStorage, MmapFileStorageHandle};
+    pub use crate::pool_storage::{HeapPoolStorage, PoolStorage, PoolStorageHandle};
+    pub use crate::segregator_storage::{SegregatorStorage, SegregatorStorageHandle};
     pub use crate::sharable_storage_wrapper::ShareableStorageWrapper;
+    #[cfg(all(feature = "unix", target_os = "linux"))]
+    pub use crate::shared_memory_storage::{SharedMemoryStorage, SharedMemoryStorageHandle};
     pub use crate::slot_storage::{SlotStorage, SlotStorageHandle};
+    pub use crate::small_storage::{SmallStorage, SmallStorageHandle};
+    pub use crate::stats_storage::{StatsStorage, StorageStats};
+    pub use crate::tlsf_storage::{TlsfStats, TlsfStorage, TlsfStorageHandle};
+    pub use crate::tracing_storage::{RingBufferSink, TraceEvent, TraceSink, TracingStorage};
 }
 
 /// The collections that use a [`Storage`] for their backing data
@@ -142,6 +231,19 @@ pub unsafe trait MultipleStorage: Storage {}
 /// This trait can only be implemented if moving `Self` will not invalidate pointers/references that have been retrived from [`Storage::resolve`]
 pub unsafe trait StableStorage: Storage {}
 
+/// A [`Storage`] whose handles can be recovered from the pointers returned by [`Storage::resolve`],
+/// this is what allows a [`Storage`] to be used through [`StorageAllocator`]
+///
+/// # Safety
+/// [`PointerStorage::handle_from_ptr`] must return the handle that [`Storage::resolve`]d to `ptr`
+pub unsafe trait PointerStorage: Storage {
+    /// Returns the handle that [`Storage::resolve`]s to `ptr`
+    ///
+    /// # Safety
+    /// `ptr` must have been returned by [`Storage::resolve`] for a valid handle to an allocation with a non-zero size
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle;
+}
+
 unsafe impl<T: MultipleStorage + ?Sized> Storage for &T {
     type Handle = T::Handle;
 
@@ -183,6 +285,11 @@ unsafe impl<T: MultipleStorage + ?Sized> ShareableStorage for &T {
         self
     }
 }
+unsafe impl<T: MultipleStorage + PointerStorage + ?Sized> PointerStorage for &T {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        unsafe { T::handle_from_ptr(self, ptr) }
+    }
+}
 
 unsafe impl<T: Storage + ?Sized> Storage for &mut T {
     type Handle = T::Handle;
@@ -220,3 +327,8 @@ unsafe impl<T: Storage + ?Sized> Storage for &mut T {
 
 unsafe impl<T: MultipleStorage + ?Sized> MultipleStorage for &mut T {}
 unsafe impl<T: Storage + ?Sized> StableStorage for &mut T {}
+unsafe impl<T: PointerStorage + ?Sized> PointerStorage for &mut T {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        unsafe { T::handle_from_ptr(self, ptr) }
+    }
+}
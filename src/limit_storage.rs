@@ -0,0 +1,158 @@
+use crate::{MultipleStorage, PointerStorage, StableStorage, Storage, StorageAllocError};
+use core::{alloc::Layout, cell::Cell, ptr::NonNull};
+
+/// A wrapper around a [`Storage`] that fails allocations once the bytes in use would go over a limit
+///
+/// The bytes in use are counted using the requested sizes, so if the inner [`Storage`] returns more space than requested
+/// then only the requested size is returned, otherwise deallocating could pass back a size that was never counted
+///
+/// Several collections can share one limit by using a reference to the same [`LimitStorage`]
+///
+/// ```
+/// use storage_api::{Global, LimitStorage, String, Vec};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let storage = LimitStorage::new(Global, 64);
+///
+/// let mut v = Vec::<u64, _>::with_capacity_in(4, &storage)?;
+/// v.extend_from_slice(&[1, 2, 3, 4])?;
+/// assert_eq!(storage.used(), 32);
+///
+/// let mut s = String::with_capacity_in(32, &storage)?; // this uses the rest of the limit
+/// assert!(v.reserve_exact(1).is_err());
+/// assert!(s.push_str(&"a".repeat(33)).is_err());
+///
+/// drop(s);
+/// assert_eq!(storage.remaining(), 32);
+/// v.reserve_exact(4)?;
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// A [`PoolStorage`](crate::PoolStorage) returns a whole slot for every allocation, but only the requested bytes are counted
+/// ```
+/// use storage_api::{Box, LimitStorage, PoolStorage};
+///
+/// let storage = LimitStorage::new(PoolStorage::<[u8; 64], 4>::new(), 128);
+/// for _ in 0..3 {
+///     drop(Box::new_in(1u8, &storage).unwrap());
+/// }
+/// assert_eq!(storage.used(), 0);
+///
+/// let b = Box::new_in(1u8, &storage).unwrap();
+/// assert_eq!(storage.used(), 1);
+/// ```
+pub struct LimitStorage<S> {
+    storage: S,
+    limit: usize,
+    used: Cell<usize>,
+}
+
+impl<S> LimitStorage<S> {
+    /// Constructs a new [`LimitStorage`] that allows at most `limit` bytes to be allocated from `storage`
+    pub const fn new(storage: S, limit: usize) -> Self {
+        Self {
+            storage,
+            limit,
+            used: Cell::new(0),
+        }
+    }
+
+    /// Returns the maximum number of bytes that can be allocated
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Sets the maximum number of bytes that can be allocated
+    ///
+    /// Lowering the limit below [`LimitStorage::used`] doesnt free anything, it just makes allocations fail until enough is deallocated
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
+    /// Returns the number of bytes currently allocated
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    /// Returns the number of bytes that can still be allocated
+    pub fn remaining(&self) -> usize {
+        self.limit.saturating_sub(self.used.get())
+    }
+
+    /// Returns a reference to the inner [`Storage`]
+    pub fn inner(&self) -> &S {
+        &self.storage
+    }
+
+    /// Returns the inner [`Storage`]
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    fn check(&self, old_size: usize, new_size: usize) -> Result<(), StorageAllocError> {
+        let used = self.used.get() - old_size;
+        match used.checked_add(new_size) {
+            Some(used) if used <= self.limit => Ok(()),
+            _ => Err(StorageAllocError),
+        }
+    }
+
+    fn record(&self, old_size: usize, new_size: usize) {
+        self.used.set(self.used.get() - old_size + new_size);
+    }
+}
+
+unsafe impl<S: Storage> Storage for LimitStorage<S> {
+    type Handle = S::Handle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { self.storage.resolve(handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        self.check(0, layout.size())?;
+        let (handle, _) = self.storage.allocate(layout)?;
+        self.record(0, layout.size());
+        Ok((handle, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        unsafe { self.storage.deallocate(layout, handle) };
+        self.record(layout.size(), 0);
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        self.check(old_layout.size(), new_layout.size())?;
+        let (handle, _) = unsafe { self.storage.grow(old_layout, new_layout, handle)? };
+        self.record(old_layout.size(), new_layout.size());
+        Ok((handle, new_layout.size()))
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let (handle, _) = unsafe { self.storage.shrink(old_layout, new_layout, handle)? };
+        self.record(old_layout.size(), new_layout.size());
+        Ok((handle, new_layout.size()))
+    }
+}
+
+unsafe impl<S: MultipleStorage> MultipleStorage for LimitStorage<S> {}
+unsafe impl<S: StableStorage> StableStorage for LimitStorage<S> {}
+unsafe impl<S: PointerStorage> PointerStorage for LimitStorage<S> {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        unsafe { self.storage.handle_from_ptr(ptr) }
+    }
+}
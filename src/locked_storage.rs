@@ -0,0 +1,172 @@
+use crate::{MultipleStorage, PointerStorage, StableStorage, Storage, StorageAllocError};
+use core::{
+    alloc::Layout,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// A lock that can be used by [`LockedStorage`]
+///
+/// # Safety
+/// Only one caller can hold the lock at a time, [`RawLock::lock`] must not return until every previous call has been matched by a call to [`RawLock::unlock`]
+pub unsafe trait RawLock {
+    /// An unlocked lock
+    const UNLOCKED: Self;
+
+    /// Waits until the lock can be acquired, and acquires it
+    fn lock(&self);
+
+    /// Releases the lock
+    ///
+    /// # Safety
+    /// The lock must be held by the caller
+    unsafe fn unlock(&self);
+}
+
+/// A [`RawLock`] that spins until it is unlocked, this can be used without `std`
+#[derive(Debug, Default)]
+pub struct SpinLock(AtomicBool);
+
+unsafe impl RawLock for SpinLock {
+    const UNLOCKED: Self = Self(AtomicBool::new(false));
+
+    fn lock(&self) {
+        while self
+            .0
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            while self.0.load(Ordering::Relaxed) {
+                core::hint::spin_loop();
+            }
+        }
+    }
+
+    unsafe fn unlock(&self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// Releases the lock when dropped, so it is released even if the inner [`Storage`] panics
+pub(crate) struct Guard<'a, L: RawLock>(&'a L);
+
+impl<'a, L: RawLock> Guard<'a, L> {
+    pub(crate) fn new(lock: &'a L) -> Self {
+        lock.lock();
+        Self(lock)
+    }
+}
+
+impl<L: RawLock> Drop for Guard<'_, L> {
+    fn drop(&mut self) {
+        unsafe { self.0.unlock() }
+    }
+}
+
+/// A wrapper around a [`Storage`] that holds a lock during every call to it,
+/// which makes it [`Sync`] even if the inner [`Storage`] is only [`Send`]
+///
+/// ```
+/// use core::mem::MaybeUninit;
+/// use storage_api::{Box, BumpStorage, LockedStorage};
+///
+/// let mut buffer = [MaybeUninit::uninit(); 1024];
+/// let storage = LockedStorage::<_>::new(BumpStorage::from_uninit(&mut buffer));
+///
+/// std::thread::scope(|scope| {
+///     for i in 0..4u64 {
+///         let storage = &storage;
+///         scope.spawn(move || {
+///             let a = Box::new_in(i, storage).unwrap();
+///             let b = Box::new_in(i * 10, storage).unwrap();
+///             assert_eq!(*a * 10, *b);
+///         });
+///     }
+/// });
+/// ```
+pub struct LockedStorage<S, L = SpinLock> {
+    lock: L,
+    storage: S,
+}
+
+unsafe impl<S: Send, L: RawLock + Sync> Sync for LockedStorage<S, L> {}
+
+impl<S, L: RawLock> LockedStorage<S, L> {
+    /// Constructs a new [`LockedStorage`] wrapping `storage`
+    pub const fn new(storage: S) -> Self {
+        Self {
+            lock: L::UNLOCKED,
+            storage,
+        }
+    }
+
+    /// Calls `f` with a reference to the inner [`Storage`] while holding the lock
+    pub fn with_inner<R>(&self, f: impl FnOnce(&S) -> R) -> R {
+        let _guard = Guard::new(&self.lock);
+        f(&self.storage)
+    }
+
+    /// Returns a mutable reference to the inner [`Storage`], no locking is needed because this borrows the [`LockedStorage`] mutably
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.storage
+    }
+
+    /// Returns the inner [`Storage`]
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+}
+
+unsafe impl<S: Storage, L: RawLock> Storage for LockedStorage<S, L> {
+    type Handle = S::Handle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        let _guard = Guard::new(&self.lock);
+        unsafe { self.storage.resolve(handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let _guard = Guard::new(&self.lock);
+        self.storage.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        let _guard = Guard::new(&self.lock);
+        unsafe { self.storage.deallocate(layout, handle) }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let _guard = Guard::new(&self.lock);
+        unsafe { self.storage.grow(old_layout, new_layout, handle) }
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let _guard = Guard::new(&self.lock);
+        unsafe { self.storage.shrink(old_layout, new_layout, handle) }
+    }
+}
+
+unsafe impl<S: MultipleStorage, L: RawLock> MultipleStorage for LockedStorage<S, L> {}
+unsafe impl<S: StableStorage, L: RawLock> StableStorage for LockedStorage<S, L> {}
+unsafe impl<S: PointerStorage, L: RawLock> PointerStorage for LockedStorage<S, L> {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        let _guard = Guard::new(&self.lock);
+        unsafe { self.storage.handle_from_ptr(ptr) }
+    }
+}
+
+impl<S: Default, L: RawLock> Default for LockedStorage<S, L> {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
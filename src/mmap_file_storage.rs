@@ -0,0 +1,370 @@
+extern crate std;
+
+use crate::{
+    MultipleStorage, PointerStorage, StableStorage, Storage, StorageAllocError, StorageHandle,
+    os::page_size,
+    size_class_heap::{HeapHeader, SizeClassHeap},
+};
+use core::{alloc::Layout, cell::Cell, ptr::NonNull};
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    os::fd::AsRawFd,
+    path::Path,
+};
+
+/// The [`StorageHandle`] for [`MmapFileStorage`],
+/// this is the offset of the allocation from the start of the file, so it stays valid when the file is reopened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MmapFileStorageHandle {
+    /// The offset of the allocation from the start of the file
+    pub offset: usize,
+}
+
+impl StorageHandle for MmapFileStorageHandle {}
+
+/// Stored at the start of the file
+#[repr(C)]
+struct FileHeader {
+    magic: [u8; 8],
+    version: u64,
+    /// the offset of the allocation set with [`MmapFileStorage::set_root`], or 0
+    root: u64,
+    heap: HeapHeader,
+}
+
+const MAGIC: [u8; 8] = *b"STORMMAP";
+const VERSION: u64 = 2;
+/// the smallest page size, mappings are always aligned to it
+const MAX_ALIGN: usize = 4096;
+
+/// A [`Storage`] that allocates inside a memory mapped file, so collections stored in it persist after the process exits
+///
+/// Handles are offsets from the start of the file, the file can be reopened by another process and at a different address and all the handles stay valid,
+/// [`MmapFileStorage::set_root`] can be used to store a handle in the file so the collections can be found again
+///
+/// The file grows when it is full, up to the `max_size` given to [`MmapFileStorage::open`], all of which is reserved up front so the mapping never moves.
+/// Allocations are made from blocks with power of 2 sizes, freed blocks are reused by allocations of the same size class,
+/// and allocations with an alignment greater than 4096 fail
+///
+/// Opening a file is `unsafe` because the mapping is only sound if nothing else uses the file while it is mapped, see [`MmapFileStorage::open`]
+///
+/// ```
+/// use storage_api::{Box, MmapFileStorage, Vec, storages::MmapFileStorageHandle};
+///
+/// # fn main() -> std::io::Result<()> {
+///
+/// #[derive(Debug, Clone, Copy)]
+/// struct Record {
+///     id: u32,
+///     value: f32,
+/// }
+///
+/// struct Root {
+///     records: MmapFileStorageHandle,
+///     length: usize,
+///     capacity: usize,
+/// }
+///
+/// let path = std::env::temp_dir().join("storage_api_mmap_file_storage_example");
+/// # _ = std::fs::remove_file(&path);
+///
+/// {
+///     let storage = unsafe { MmapFileStorage::open(&path, 1 << 30)? };
+///     let mut records = Vec::<Record, _>::new_in(&storage).unwrap();
+///     for id in 0..10000 {
+///         records.push(Record { id, value: id as f32 / 2.0 }).unwrap();
+///     }
+///
+///     let (_, records, length, capacity) = records.into_raw_parts();
+///     let root = Box::new_in(Root { records, length, capacity }, &storage).unwrap();
+///     storage.set_root(Some(Box::into_raw_parts(root).1));
+///     storage.flush()?;
+/// }
+///
+/// let storage = unsafe { MmapFileStorage::open(&path, 1 << 30)? };
+/// let root = unsafe { Box::<Root, _>::from_raw_parts(&storage, storage.root().unwrap(), ()) };
+/// let records = unsafe {
+///     Vec::<Record, _>::from_raw_parts(&storage, root.records, root.length, root.capacity)
+/// };
+/// assert_eq!(records.len(), 10000);
+/// assert!(records.iter().enumerate().all(|(i, r)| r.id == i as u32 && r.value == i as f32 / 2.0));
+///
+/// # drop((records, root));
+/// # std::fs::remove_file(&path)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct MmapFileStorage {
+    file: File,
+    base: NonNull<u8>,
+    /// the size of the address range reserved for the mapping
+    reserved: usize,
+    /// the length of the file, all of which is mapped
+    length: Cell<usize>,
+}
+
+unsafe impl Send for MmapFileStorage {}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl MmapFileStorage {
+    /// Opens the file at `path` and maps it, the file is created if it doesnt exist
+    ///
+    /// `max_size` is the largest the file can grow to, returns an error if the file is already larger than this,
+    /// or if it isnt empty and wasnt created by [`MmapFileStorage`].
+    /// The header and the free lists are checked, but this cant catch every way a file could be corrupted
+    ///
+    /// # Safety
+    /// - the file must not be opened by another [`MmapFileStorage`], in this or another process, until this one is dropped
+    /// - the file must not be modified or truncated by anything else while it is mapped
+    /// - if the file isnt empty it must have been written by a [`MmapFileStorage`]
+    pub unsafe fn open(path: impl AsRef<Path>, max_size: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let length = usize::try_from(file.metadata()?.len())
+            .map_err(|_| invalid_data("the file is too large to map"))?;
+
+        let reserved = max_size
+            .max(size_of::<FileHeader>())
+            .checked_next_multiple_of(page_size())
+            .ok_or(io::ErrorKind::InvalidInput)?;
+        if length > reserved {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the file is larger than max_size",
+            ));
+        }
+
+        let base = unsafe {
+            libc::mmap(
+                core::ptr::null_mut(),
+                reserved,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE,
+                -1,
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        let this = Self {
+            file,
+            base: NonNull::new(base.cast()).ok_or(io::ErrorKind::OutOfMemory)?,
+            reserved,
+            length: Cell::new(0),
+        };
+        if length == 0 {
+            this.resize(size_of::<FileHeader>().next_multiple_of(page_size()))?;
+            unsafe {
+                this.header().write(FileHeader {
+                    magic: MAGIC,
+                    version: VERSION,
+                    root: 0,
+                    heap: HeapHeader::new(size_of::<FileHeader>()),
+                });
+            }
+        } else {
+            if length < size_of::<FileHeader>() {
+                return Err(invalid_data(
+                    "the file is too small to be a MmapFileStorage",
+                ));
+            }
+            this.map(length)?;
+
+            let header = unsafe { this.header().as_ref() };
+            if header.magic != MAGIC {
+                return Err(invalid_data("the file wasnt created by a MmapFileStorage"));
+            }
+            if header.version != VERSION {
+                return Err(invalid_data(
+                    "the file was created by an unsupported version",
+                ));
+            }
+            if !this.heap().validate(size_of::<FileHeader>(), length)
+                || this.root().is_some_and(|root| {
+                    !this
+                        .heap()
+                        .contains(size_of::<FileHeader>(), root.offset, Layout::new::<()>())
+                })
+            {
+                return Err(invalid_data("the file is corrupted"));
+            }
+        }
+        Ok(this)
+    }
+
+    /// Returns the current length of the file in bytes
+    pub fn file_len(&self) -> usize {
+        self.length.get()
+    }
+
+    /// Returns the largest the file can grow to in bytes
+    pub fn max_size(&self) -> usize {
+        self.reserved
+    }
+
+    /// Returns the handle stored with [`MmapFileStorage::set_root`]
+    pub fn root(&self) -> Option<MmapFileStorageHandle> {
+        let root = unsafe { (*self.header().as_ptr()).root };
+        (root != 0).then_some(MmapFileStorageHandle {
+            offset: root as usize,
+        })
+    }
+
+    /// Stores `root` in the file, so it can be retrieved with [`MmapFileStorage::root`] after reopening the file
+    pub fn set_root(&self, root: Option<MmapFileStorageHandle>) {
+        let root = root.map_or(0, |root| root.offset as u64);
+        unsafe { (*self.header().as_ptr()).root = root };
+    }
+
+    /// Writes all changes to the file to disk, and waits for it to finish
+    pub fn flush(&self) -> io::Result<()> {
+        if unsafe { libc::msync(self.base.as_ptr().cast(), self.length.get(), libc::MS_SYNC) } != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        self.file.sync_data()
+    }
+
+    /// Maps the first `length` bytes of the file over the start of the reserved range
+    fn map(&self, length: usize) -> io::Result<()> {
+        let ptr = unsafe {
+            libc::mmap(
+                self.base.as_ptr().cast(),
+                length,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_FIXED,
+                self.file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        self.length.set(length);
+        Ok(())
+    }
+
+    /// Changes the length of the file with `ftruncate` and remaps it
+    fn resize(&self, length: usize) -> io::Result<()> {
+        self.file.set_len(length as u64)?;
+        self.map(length)
+    }
+
+    /// Grows the file so that it is at least `end` bytes long
+    fn reserve(&self, end: usize) -> Result<(), StorageAllocError> {
+        let length = self.length.get();
+        if end <= length {
+            return Ok(());
+        }
+        if end > self.reserved {
+            return Err(StorageAllocError);
+        }
+
+        let new_length = end
+            .max(length.saturating_mul(2))
+            .next_multiple_of(page_size())
+            .min(self.reserved);
+        self.resize(new_length).map_err(|_| StorageAllocError)
+    }
+
+    fn header(&self) -> NonNull<FileHeader> {
+        self.base.cast()
+    }
+
+    fn heap(&self) -> SizeClassHeap {
+        unsafe {
+            SizeClassHeap::new(
+                self.base,
+                NonNull::new_unchecked(&raw mut (*self.header().as_ptr()).heap),
+                MAX_ALIGN,
+            )
+        }
+    }
+}
+
+unsafe impl Storage for MmapFileStorage {
+    type Handle = MmapFileStorageHandle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { self.base.add(handle.offset).cast() }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let (offset, size) = self
+            .heap()
+            .allocate(layout, |range| self.reserve(range.end))?;
+        Ok((MmapFileStorageHandle { offset }, size))
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        _ = layout;
+        unsafe { self.heap().deallocate(handle.offset) }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        if let Some(size) = unsafe { self.heap().resize_in_place(handle.offset, new_layout) } {
+            return Ok((handle, size));
+        }
+
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            self.resolve(new_handle)
+                .cast::<u8>()
+                .copy_from_nonoverlapping(self.resolve(handle).cast(), old_layout.size());
+            self.deallocate(old_layout, handle);
+        }
+        Ok((new_handle, new_size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        if let Some(size) = unsafe { self.heap().resize_in_place(handle.offset, new_layout) } {
+            return Ok((handle, size));
+        }
+
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            self.resolve(new_handle)
+                .cast::<u8>()
+                .copy_from_nonoverlapping(self.resolve(handle).cast(), new_layout.size());
+            self.deallocate(old_layout, handle);
+        }
+        Ok((new_handle, new_size))
+    }
+}
+
+/// The whole address range is reserved when the file is opened, so growing the file never moves the mapping
+unsafe impl MultipleStorage for MmapFileStorage {}
+unsafe impl StableStorage for MmapFileStorage {}
+unsafe impl PointerStorage for MmapFileStorage {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        MmapFileStorageHandle {
+            offset: ptr.addr().get() - self.base.addr().get(),
+        }
+    }
+}
+
+impl Drop for MmapFileStorage {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.base.as_ptr().cast(), self.reserved) };
+    }
+}
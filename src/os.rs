@@ -0,0 +1,4 @@
+/// Returns the size of a page of memory
+pub(crate) fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
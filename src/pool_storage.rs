@@ -0,0 +1,316 @@
+use crate::{
+    Global, MultipleStorage, PointerStorage, StableStorage, Storage, StorageAllocError,
+    StorageHandle, global_storage::GlobalHandle,
+};
+use core::{
+    alloc::Layout,
+    cell::{Cell, UnsafeCell},
+    mem::{ManuallyDrop, MaybeUninit},
+    ptr::NonNull,
+};
+
+/// The [`StorageHandle`] for [`PoolStorage`] and [`HeapPoolStorage`],
+/// this is the index of a slot in the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PoolStorageHandle(u32);
+
+impl StorageHandle for PoolStorageHandle {}
+
+impl PoolStorageHandle {
+    /// The handle given out for zero sized allocations, which dont need a slot
+    const EMPTY: Self = Self(u32::MAX);
+}
+
+/// A single slot in a pool, while the slot is free it stores the index of the next free slot
+#[repr(C)]
+union PoolSlot<T> {
+    next: u32,
+    _value: ManuallyDrop<MaybeUninit<T>>,
+}
+
+/// The intrusive list of free slots in a pool
+struct FreeList {
+    head: Cell<u32>,
+    /// slots at or after this index have never been allocated, so they arent in the list yet
+    initialised: Cell<u32>,
+}
+
+impl FreeList {
+    const fn new() -> Self {
+        Self {
+            head: Cell::new(u32::MAX),
+            initialised: Cell::new(0),
+        }
+    }
+
+    fn allocate<T>(
+        &self,
+        slots: NonNull<PoolSlot<T>>,
+        length: u32,
+        layout: Layout,
+    ) -> Result<(PoolStorageHandle, usize), StorageAllocError> {
+        if layout.size() > size_of::<PoolSlot<T>>() || layout.align() > align_of::<PoolSlot<T>>() {
+            return Err(StorageAllocError);
+        }
+        if layout.size() == 0 {
+            return Ok((PoolStorageHandle::EMPTY, 0));
+        }
+
+        let index = match self.head.get() {
+            u32::MAX => {
+                let index = self.initialised.get();
+                if index >= length {
+                    return Err(StorageAllocError);
+                }
+                self.initialised.set(index + 1);
+                index
+            }
+            index => {
+                self.head
+                    .set(unsafe { slots.add(index as usize).as_ref().next });
+                index
+            }
+        };
+        Ok((PoolStorageHandle(index), size_of::<PoolSlot<T>>()))
+    }
+
+    unsafe fn deallocate<T>(&self, slots: NonNull<PoolSlot<T>>, handle: PoolStorageHandle) {
+        if handle == PoolStorageHandle::EMPTY {
+            return;
+        }
+
+        unsafe {
+            slots.add(handle.0 as usize).write(PoolSlot {
+                next: self.head.get(),
+            });
+        }
+        self.head.set(handle.0);
+    }
+
+    unsafe fn resolve<T>(slots: NonNull<PoolSlot<T>>, handle: PoolStorageHandle) -> NonNull<()> {
+        if handle == PoolStorageHandle::EMPTY {
+            NonNull::<PoolSlot<T>>::dangling().cast()
+        } else {
+            unsafe { slots.add(handle.0 as usize).cast() }
+        }
+    }
+
+    unsafe fn resize<T>(
+        &self,
+        slots: NonNull<PoolSlot<T>>,
+        length: u32,
+        new_layout: Layout,
+        handle: PoolStorageHandle,
+    ) -> Result<(PoolStorageHandle, usize), StorageAllocError> {
+        if handle == PoolStorageHandle::EMPTY {
+            return self.allocate(slots, length, new_layout);
+        }
+        if new_layout.size() > size_of::<PoolSlot<T>>()
+            || new_layout.align() > align_of::<PoolSlot<T>>()
+        {
+            return Err(StorageAllocError);
+        }
+        Ok((handle, size_of::<PoolSlot<T>>()))
+    }
+}
+
+/// A pool of `N` slots, each with the size/alignment requirements of `T`, stored inline
+///
+/// Every allocation takes up a whole slot, so allocations that dont fit in a `T` will fail.
+/// The [`Storage::Handle`] is the index of the slot, so it is only 4 bytes and stays valid even if the [`PoolStorage`] is moved
+///
+/// ```
+/// use storage_api::{Box, PoolStorage, storages::PoolStorageHandle};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// assert_eq!(size_of::<PoolStorageHandle>(), 4);
+///
+/// let storage = PoolStorage::<u64, 16>::new();
+/// let mut boxes = (0..16)
+///     .map(|i| Box::new_in(i, &storage))
+///     .collect::<Result<std::vec::Vec<_>, _>>()?;
+/// assert_eq!(Box::new_in(16, &storage).err(), Some(StorageAllocError)); // every slot is in use
+/// assert_eq!(Box::new_in(0u128, &storage).err(), Some(StorageAllocError)); // doesnt fit in a slot
+///
+/// drop(boxes.remove(3)); // frees a slot
+/// boxes.push(Box::new_in(16, &storage)?);
+/// assert_eq!(boxes.iter().map(|b| **b).sum::<u64>(), (0..=16).sum::<u64>() - 3);
+///
+/// # Ok(())
+/// # }
+/// ```
+pub struct PoolStorage<T, const N: usize> {
+    slots: UnsafeCell<MaybeUninit<[PoolSlot<T>; N]>>,
+    free: FreeList,
+}
+
+unsafe impl<T, const N: usize> Send for PoolStorage<T, N> {}
+
+impl<T, const N: usize> PoolStorage<T, N> {
+    /// Constructs a new [`PoolStorage`] with every slot free
+    pub const fn new() -> Self {
+        const {
+            assert!(
+                N < u32::MAX as usize,
+                "the number of slots must fit in the handle"
+            )
+        };
+        Self {
+            slots: UnsafeCell::new(MaybeUninit::uninit()),
+            free: FreeList::new(),
+        }
+    }
+
+    /// Returns the total number of slots in the pool
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    fn slots(&self) -> NonNull<PoolSlot<T>> {
+        unsafe { NonNull::new_unchecked(self.slots.get().cast()) }
+    }
+}
+
+unsafe impl<T, const N: usize> Storage for PoolStorage<T, N> {
+    type Handle = PoolStorageHandle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { FreeList::resolve(self.slots(), handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        self.free.allocate(self.slots(), N as u32, layout)
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        _ = layout;
+        unsafe { self.free.deallocate(self.slots(), handle) }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        _ = old_layout;
+        unsafe { self.free.resize(self.slots(), N as u32, new_layout, handle) }
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        _ = old_layout;
+        unsafe { self.free.resize(self.slots(), N as u32, new_layout, handle) }
+    }
+}
+
+unsafe impl<T, const N: usize> MultipleStorage for PoolStorage<T, N> {}
+
+impl<T, const N: usize> Default for PoolStorage<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A pool of slots allocated from [`Global`], each with the size/alignment requirements of `T`
+///
+/// This is the same as [`PoolStorage`] but the number of slots is chosen at runtime
+pub struct HeapPoolStorage<T> {
+    slots: NonNull<PoolSlot<T>>,
+    length: u32,
+    free: FreeList,
+}
+
+unsafe impl<T> Send for HeapPoolStorage<T> {}
+
+impl<T> HeapPoolStorage<T> {
+    /// Constructs a new [`HeapPoolStorage`] with `capacity` free slots
+    pub fn with_capacity(capacity: usize) -> Result<Self, StorageAllocError> {
+        let length = u32::try_from(capacity)
+            .ok()
+            .filter(|&length| length != u32::MAX)
+            .ok_or(StorageAllocError)?;
+        let layout = Layout::array::<PoolSlot<T>>(capacity).map_err(|_| StorageAllocError)?;
+        let (handle, _) = Global.allocate(layout)?;
+        Ok(Self {
+            slots: handle.0.cast(),
+            length,
+            free: FreeList::new(),
+        })
+    }
+
+    /// Returns the total number of slots in the pool
+    pub fn capacity(&self) -> usize {
+        self.length as usize
+    }
+}
+
+unsafe impl<T> Storage for HeapPoolStorage<T> {
+    type Handle = PoolStorageHandle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { FreeList::resolve(self.slots, handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        self.free.allocate(self.slots, self.length, layout)
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        _ = layout;
+        unsafe { self.free.deallocate(self.slots, handle) }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        _ = old_layout;
+        unsafe {
+            self.free
+                .resize(self.slots, self.length, new_layout, handle)
+        }
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        _ = old_layout;
+        unsafe {
+            self.free
+                .resize(self.slots, self.length, new_layout, handle)
+        }
+    }
+}
+
+unsafe impl<T> MultipleStorage for HeapPoolStorage<T> {}
+unsafe impl<T> StableStorage for HeapPoolStorage<T> {}
+unsafe impl<T> PointerStorage for HeapPoolStorage<T> {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        PoolStorageHandle(
+            ((ptr.addr().get() - self.slots.addr().get()) / size_of::<PoolSlot<T>>()) as u32,
+        )
+    }
+}
+
+impl<T> Drop for HeapPoolStorage<T> {
+    fn drop(&mut self) {
+        unsafe {
+            Global.deallocate(
+                Layout::array::<PoolSlot<T>>(self.length as usize).unwrap_unchecked(),
+                GlobalHandle(self.slots.cast()),
+            );
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{
+    MultipleStorage, ShareableStorage, StableStorage, Storage, StorageAllocError, StorageHandle,
+};
+use core::{alloc::Layout, ptr::NonNull};
+
+/// The [`StorageHandle`] for [`SegregatorStorage`],
+/// this remembers which [`Storage`] the allocation is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SegregatorStorageHandle<S, L> {
+    /// The allocation is in the [`Storage`] for small allocations
+    Small(S),
+    /// The allocation is in the [`Storage`] for large allocations
+    Large(L),
+}
+
+impl<S: StorageHandle, L: StorageHandle> StorageHandle for SegregatorStorageHandle<S, L> {}
+
+/// A [`Storage`] that sends allocations of at most `THRESHOLD` bytes to one [`Storage`], and larger allocations to another
+///
+/// Growing an allocation past `THRESHOLD` moves it to the [`Storage`] for large allocations,
+/// and shrinking it back down moves it to the [`Storage`] for small allocations if that has room for it
+///
+/// ```
+/// use storage_api::{Global, HeapPoolStorage, SegregatorStorage, Vec, storages::SegregatorStorageHandle};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// // allocations of up to 32 bytes come from the pool, everything else from `Global`
+/// let storage = SegregatorStorage::<32, _, _>::new(HeapPoolStorage::<[u8; 32]>::with_capacity(64)?, Global);
+///
+/// let mut v = Vec::<u8, _>::new_in(&storage)?;
+/// v.extend_from_slice(b"small")?;
+/// assert_eq!(v.capacity(), 32); // a whole slot of the pool
+///
+/// v.extend_from_slice(&[0; 100])?; // this moves `v` to `Global`
+/// let (storage, handle, length, capacity) = v.into_raw_parts();
+/// assert!(matches!(handle, SegregatorStorageHandle::Large(_)));
+/// let v = unsafe { Vec::<u8, _>::from_raw_parts(storage, handle, length, capacity) };
+/// assert_eq!(&v[..5], b"small");
+///
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default, Clone, Copy)]
+pub struct SegregatorStorage<const THRESHOLD: usize, Small, Large> {
+    small: Small,
+    large: Large,
+}
+
+impl<const THRESHOLD: usize, Small, Large> SegregatorStorage<THRESHOLD, Small, Large> {
+    /// Constructs a new [`SegregatorStorage`] from the [`Storage`]s for small and large allocations
+    pub const fn new(small: Small, large: Large) -> Self {
+        Self { small, large }
+    }
+
+    /// Returns a reference to the [`Storage`] for small allocations
+    pub fn small(&self) -> &Small {
+        &self.small
+    }
+
+    /// Returns a reference to the [`Storage`] for large allocations
+    pub fn large(&self) -> &Large {
+        &self.large
+    }
+
+    /// Splits the [`SegregatorStorage`] into the [`Storage`]s for small and large allocations
+    pub fn into_inner(self) -> (Small, Large) {
+        (self.small, self.large)
+    }
+}
+
+unsafe impl<const THRESHOLD: usize, Small: Storage, Large: Storage> Storage
+    for SegregatorStorage<THRESHOLD, Small, Large>
+{
+    type Handle = SegregatorStorageHandle<Small::Handle, Large::Handle>;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        match handle {
+            SegregatorStorageHandle::Small(handle) => unsafe { self.small.resolve(handle) },
+            SegregatorStorageHandle::Large(handle) => unsafe { self.large.resolve(handle) },
+        }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        if layout.size() <= THRESHOLD {
+            let (handle, size) = self.small.allocate(layout)?;
+            Ok((SegregatorStorageHandle::Small(handle), size))
+        } else {
+            let (handle, size) = self.large.allocate(layout)?;
+            Ok((SegregatorStorageHandle::Large(handle), size))
+        }
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        match handle {
+            SegregatorStorageHandle::Small(handle) => unsafe {
+                self.small.deallocate(layout, handle)
+            },
+            SegregatorStorageHandle::Large(handle) => unsafe {
+                self.large.deallocate(layout, handle)
+            },
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        match handle {
+            SegregatorStorageHandle::Small(handle) if new_layout.size() <= THRESHOLD => {
+                let (handle, size) = unsafe { self.small.grow(old_layout, new_layout, handle)? };
+                Ok((SegregatorStorageHandle::Small(handle), size))
+            }
+            SegregatorStorageHandle::Small(handle) => {
+                let (new_handle, size) = self.large.allocate(new_layout)?;
+                unsafe {
+                    self.large
+                        .resolve(new_handle)
+                        .cast::<u8>()
+                        .copy_from_nonoverlapping(
+                            self.small.resolve(handle).cast(),
+                            old_layout.size(),
+                        );
+                    self.small.deallocate(old_layout, handle);
+                }
+                Ok((SegregatorStorageHandle::Large(new_handle), size))
+            }
+            SegregatorStorageHandle::Large(handle) => {
+                let (handle, size) = unsafe { self.large.grow(old_layout, new_layout, handle)? };
+                Ok((SegregatorStorageHandle::Large(handle), size))
+            }
+        }
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        match handle {
+            SegregatorStorageHandle::Small(handle) => {
+                let (handle, size) = unsafe { self.small.shrink(old_layout, new_layout, handle)? };
+                Ok((SegregatorStorageHandle::Small(handle), size))
+            }
+            SegregatorStorageHandle::Large(handle) => {
+                if new_layout.size() <= THRESHOLD
+                    && let Ok((new_handle, size)) = self.small.allocate(new_layout)
+                {
+                    unsafe {
+                        self.small
+                            .resolve(new_handle)
+                            .cast::<u8>()
+                            .copy_from_nonoverlapping(
+                                self.large.resolve(handle).cast(),
+                                new_layout.size(),
+                            );
+                        self.large.deallocate(old_layout, handle);
+                    }
+                    return Ok((SegregatorStorageHandle::Small(new_handle), size));
+                }
+
+                let (handle, size) = unsafe { self.large.shrink(old_layout, new_layout, handle)? };
+                Ok((SegregatorStorageHandle::Large(handle), size))
+            }
+        }
+    }
+}
+
+unsafe impl<const THRESHOLD: usize, Small: ShareableStorage, Large: ShareableStorage>
+    ShareableStorage for SegregatorStorage<THRESHOLD, Small, Large>
+{
+    unsafe fn make_shared_copy(&self) -> Self {
+        unsafe {
+            Self {
+                small: self.small.make_shared_copy(),
+                large: self.large.make_shared_copy(),
+            }
+        }
+    }
+}
+
+unsafe impl<const THRESHOLD: usize, Small: MultipleStorage, Large: MultipleStorage> MultipleStorage
+    for SegregatorStorage<THRESHOLD, Small, Large>
+{
+}
+unsafe impl<const THRESHOLD: usize, Small: StableStorage, Large: StableStorage> StableStorage
+    for SegregatorStorage<THRESHOLD, Small, Large>
+{
+}
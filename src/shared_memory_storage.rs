@@ -0,0 +1,290 @@
+extern crate std;
+
+use crate::{
+    MultipleStorage, PointerStorage, StableStorage, Storage, StorageAllocError, StorageHandle,
+    locked_storage::{Guard, RawLock, SpinLock},
+    size_class_heap::{HeapHeader, SizeClassHeap},
+};
+use core::{alloc::Layout, ptr::NonNull};
+use std::{
+    fs::File,
+    io,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+};
+
+/// The [`StorageHandle`] for [`SharedMemoryStorage`],
+/// this is the offset of the allocation from the start of the shared memory, so it can be sent to other processes that have mapped it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SharedMemoryStorageHandle {
+    /// The offset of the allocation from the start of the shared memory
+    pub offset: usize,
+}
+
+impl StorageHandle for SharedMemoryStorageHandle {}
+
+/// Stored at the start of the shared memory
+#[repr(C)]
+struct SharedHeader {
+    magic: [u8; 8],
+    version: u64,
+    /// held while using the heap, this works between processes because it is in the shared memory
+    lock: SpinLock,
+    heap: HeapHeader,
+}
+
+const MAGIC: [u8; 8] = *b"STORSHMM";
+const VERSION: u64 = 1;
+/// the smallest page size, mappings are always aligned to it
+const MAX_ALIGN: usize = 4096;
+
+/// A [`Storage`] that allocates from memory shared between processes, created with `memfd_create`
+///
+/// Handles are offsets from the start of the shared memory, so every process that maps it with [`SharedMemoryStorage::from_fd`] can use them even though it is mapped at a different address,
+/// this means collections can be sent to another process by sending the parts from [`Vec::into_raw_parts`](crate::Vec::into_raw_parts) or [`String::into_raw_parts`](crate::String::into_raw_parts)
+///
+/// The state of the allocator is stored in the shared memory and protected by a [`SpinLock`], so every process can allocate from it at the same time.
+/// If a process exits while holding the lock then no other process can allocate, and allocations made by a process are not freed when it exits
+///
+/// Allocations are made from blocks with power of 2 sizes, freed blocks are reused by allocations of the same size class,
+/// and allocations with an alignment greater than 4096 fail
+///
+/// ```
+/// use storage_api::{SharedMemoryStorage, String};
+///
+/// # fn main() -> std::io::Result<()> {
+///
+/// let parent = SharedMemoryStorage::new(1 << 20)?;
+/// // this would normally be in another process, that received the file descriptor
+/// let child = unsafe { SharedMemoryStorage::from_fd(parent.try_clone_fd()?)? };
+///
+/// let s = String::from_str_in("hello from the parent", &parent).unwrap();
+/// let (_, handle, length, capacity) = s.into_raw_parts();
+///
+/// let mut s = unsafe { String::from_raw_parts(&child, handle, length, capacity) };
+/// assert_eq!(&*s, "hello from the parent");
+/// s.push_str(", and the child").unwrap();
+///
+/// let (_, handle, length, capacity) = s.into_raw_parts();
+/// let s = unsafe { String::from_raw_parts(&parent, handle, length, capacity) };
+/// assert_eq!(&*s, "hello from the parent, and the child");
+///
+/// # Ok(())
+/// # }
+/// ```
+pub struct SharedMemoryStorage {
+    file: File,
+    base: NonNull<u8>,
+    size: usize,
+}
+
+unsafe impl Send for SharedMemoryStorage {}
+unsafe impl Sync for SharedMemoryStorage {}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl SharedMemoryStorage {
+    /// Creates shared memory of `size` bytes and maps it
+    pub fn new(size: usize) -> io::Result<Self> {
+        let size = size.max(size_of::<SharedHeader>());
+        let fd = unsafe { libc::memfd_create(c"storage_api".as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let file = File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+        file.set_len(size as u64)?;
+
+        let this = Self::map(file, size)?;
+        unsafe {
+            this.header().write(SharedHeader {
+                magic: MAGIC,
+                version: VERSION,
+                lock: SpinLock::UNLOCKED,
+                heap: HeapHeader::new(size_of::<SharedHeader>()),
+            });
+        }
+        Ok(this)
+    }
+
+    /// Maps shared memory that was created by [`SharedMemoryStorage::new`] in this or another process
+    ///
+    /// Returns an error if `fd` isnt shared memory created by [`SharedMemoryStorage::new`].
+    /// The header and the free lists are checked, but this cant catch every way the memory could be corrupted
+    ///
+    /// # Safety
+    /// - `fd` must be shared memory created by [`SharedMemoryStorage::new`], or memory that isnt used by anything else
+    /// - every process that maps the memory must only modify it through a [`SharedMemoryStorage`] or allocations made from one,
+    ///   because the state of the allocator is stored in it and can be changed by them at any time
+    pub unsafe fn from_fd(fd: OwnedFd) -> io::Result<Self> {
+        let file = File::from(fd);
+        let size = usize::try_from(file.metadata()?.len())
+            .map_err(|_| invalid_data("the shared memory is too large to map"))?;
+        if size < size_of::<SharedHeader>() {
+            return Err(invalid_data(
+                "the shared memory is too small to be a SharedMemoryStorage",
+            ));
+        }
+
+        let this = Self::map(file, size)?;
+        let header = unsafe { this.header().as_ref() };
+        if header.magic != MAGIC {
+            return Err(invalid_data(
+                "the shared memory wasnt created by a SharedMemoryStorage",
+            ));
+        }
+        if header.version != VERSION {
+            return Err(invalid_data(
+                "the shared memory was created by an unsupported version",
+            ));
+        }
+        let (guard, heap) = this.lock();
+        if !heap.validate(size_of::<SharedHeader>(), size) {
+            return Err(invalid_data("the shared memory is corrupted"));
+        }
+        drop(guard);
+        Ok(this)
+    }
+
+    /// Duplicates the file descriptor of the shared memory, so it can be sent to another process
+    pub fn try_clone_fd(&self) -> io::Result<OwnedFd> {
+        self.file.as_fd().try_clone_to_owned()
+    }
+
+    /// Returns the size of the shared memory in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn map(file: File, size: usize) -> io::Result<Self> {
+        let base = unsafe {
+            libc::mmap(
+                core::ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            file,
+            base: NonNull::new(base.cast()).ok_or(io::ErrorKind::OutOfMemory)?,
+            size,
+        })
+    }
+
+    fn header(&self) -> NonNull<SharedHeader> {
+        self.base.cast()
+    }
+
+    /// Locks the heap, the lock is released when the [`Guard`] is dropped
+    fn lock(&self) -> (Guard<'_, SpinLock>, SizeClassHeap) {
+        unsafe {
+            let header = self.header().as_ptr();
+            (
+                Guard::new(&(*header).lock),
+                SizeClassHeap::new(
+                    self.base,
+                    NonNull::new_unchecked(&raw mut (*header).heap),
+                    MAX_ALIGN,
+                ),
+            )
+        }
+    }
+}
+
+impl AsFd for SharedMemoryStorage {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+unsafe impl Storage for SharedMemoryStorage {
+    type Handle = SharedMemoryStorageHandle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { self.base.add(handle.offset).cast() }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let (_guard, heap) = self.lock();
+        let (offset, size) = heap.allocate(layout, |range| {
+            if range.end <= self.size {
+                Ok(())
+            } else {
+                Err(StorageAllocError)
+            }
+        })?;
+        Ok((SharedMemoryStorageHandle { offset }, size))
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        _ = layout;
+        let (_guard, heap) = self.lock();
+        unsafe { heap.deallocate(handle.offset) }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let (guard, heap) = self.lock();
+        if let Some(size) = unsafe { heap.resize_in_place(handle.offset, new_layout) } {
+            return Ok((handle, size));
+        }
+        drop(guard);
+
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            self.resolve(new_handle)
+                .cast::<u8>()
+                .copy_from_nonoverlapping(self.resolve(handle).cast(), old_layout.size());
+            self.deallocate(old_layout, handle);
+        }
+        Ok((new_handle, new_size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let (guard, heap) = self.lock();
+        if let Some(size) = unsafe { heap.resize_in_place(handle.offset, new_layout) } {
+            return Ok((handle, size));
+        }
+        drop(guard);
+
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            self.resolve(new_handle)
+                .cast::<u8>()
+                .copy_from_nonoverlapping(self.resolve(handle).cast(), new_layout.size());
+            self.deallocate(old_layout, handle);
+        }
+        Ok((new_handle, new_size))
+    }
+}
+
+unsafe impl MultipleStorage for SharedMemoryStorage {}
+unsafe impl StableStorage for SharedMemoryStorage {}
+unsafe impl PointerStorage for SharedMemoryStorage {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        SharedMemoryStorageHandle {
+            offset: ptr.addr().get() - self.base.addr().get(),
+        }
+    }
+}
+
+impl Drop for SharedMemoryStorage {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.base.as_ptr().cast(), self.size) };
+    }
+}
@@ -0,0 +1,246 @@
+extern crate alloc;
+
+use crate::StorageAllocError;
+use alloc::vec::Vec;
+use core::{alloc::Layout, ops::Range, ptr::NonNull};
+
+/// The state of a [`SizeClassHeap`], this is stored inside the memory that it manages so that it can be persisted or shared between processes
+#[repr(C)]
+pub(crate) struct HeapHeader {
+    /// where the next block will be allocated from if there is no free block of the right size
+    top: u64,
+    /// the first free block of every size class
+    free: [u64; CLASSES],
+}
+
+impl HeapHeader {
+    /// Constructs the state of an empty heap that starts allocating at `top`
+    pub(crate) const fn new(top: usize) -> Self {
+        Self {
+            top: top as u64,
+            free: [NONE; CLASSES],
+        }
+    }
+
+    /// Returns where the next block will be allocated from if there is no free block of the right size
+    pub(crate) fn top(&self) -> usize {
+        self.top as usize
+    }
+}
+
+/// No block can start at offset 0 because the memory always starts with a header
+const NONE: u64 = 0;
+/// this is fixed so that the [`HeapHeader`] is the same size on every target
+const CLASSES: usize = 64;
+/// the smallest block, blocks are powers of 2 in size
+const MIN_BLOCK_SIZE: usize = 32;
+/// stored directly before the start of every allocation, this is the start of the block and its size class
+const BLOCK_HEADER_SIZE: usize = 2 * size_of::<u64>();
+
+/// An allocator that manages memory using only offsets from its start, so it works wherever the memory is mapped
+///
+/// Allocations are made from blocks with power of 2 sizes, freed blocks are put in a list for their size class and reused by allocations of the same size class
+///
+/// Blocks are aligned to their size up to `max_align`, so allocations with a greater alignment fail
+pub(crate) struct SizeClassHeap {
+    base: NonNull<u8>,
+    header: NonNull<HeapHeader>,
+    max_align: usize,
+}
+
+impl SizeClassHeap {
+    /// Constructs a [`SizeClassHeap`] that manages the memory starting at `base`
+    ///
+    /// # Safety
+    /// - `base` must be aligned to `max_align`, which must be a power of 2
+    /// - `header` must point to a valid [`HeapHeader`] that is only accessed through this [`SizeClassHeap`]
+    /// - the memory from `base` up to the top of the heap must stay valid for as long as this [`SizeClassHeap`] is used
+    pub(crate) unsafe fn new(
+        base: NonNull<u8>,
+        header: NonNull<HeapHeader>,
+        max_align: usize,
+    ) -> Self {
+        Self {
+            base,
+            header,
+            max_align,
+        }
+    }
+
+    unsafe fn read(&self, offset: usize) -> u64 {
+        unsafe { self.base.add(offset).cast::<u64>().read() }
+    }
+
+    unsafe fn write(&self, offset: usize, value: u64) {
+        unsafe { self.base.add(offset).cast::<u64>().write(value) }
+    }
+
+    /// Returns the start of the block and its size for the allocation at `offset`
+    unsafe fn block(&self, offset: usize) -> (usize, usize) {
+        unsafe {
+            let start = self.read(offset - BLOCK_HEADER_SIZE);
+            let class = self.read(offset - size_of::<u64>());
+            (start as usize, 1 << class)
+        }
+    }
+
+    /// Allocates a block for `layout`, calling `reserve` with the memory past the top of the heap that will be used if the block comes from there,
+    /// this includes the padding before the block
+    ///
+    /// Returns the offset of the allocation and its size
+    pub(crate) fn allocate(
+        &self,
+        layout: Layout,
+        reserve: impl FnOnce(Range<usize>) -> Result<(), StorageAllocError>,
+    ) -> Result<(usize, usize), StorageAllocError> {
+        if layout.align() > self.max_align {
+            return Err(StorageAllocError);
+        }
+        let data_offset = layout.align().max(BLOCK_HEADER_SIZE);
+        let block_size = data_offset
+            .checked_add(layout.size())
+            .and_then(|size| size.max(MIN_BLOCK_SIZE).checked_next_power_of_two())
+            .ok_or(StorageAllocError)?;
+        let class = block_size.trailing_zeros() as usize;
+
+        let header = self.header.as_ptr();
+        let start = match unsafe { (*header).free[class] } {
+            NONE => {
+                let top = unsafe { (*header).top as usize };
+                let start = top
+                    .checked_next_multiple_of(block_size.min(self.max_align))
+                    .ok_or(StorageAllocError)?;
+                let end = start.checked_add(block_size).ok_or(StorageAllocError)?;
+                reserve(top..end)?;
+                unsafe { (*header).top = end as u64 };
+                start
+            }
+            start => {
+                unsafe { (*header).free[class] = self.read(start as usize) };
+                start as usize
+            }
+        };
+
+        let data = start + data_offset;
+        unsafe {
+            self.write(data - BLOCK_HEADER_SIZE, start as u64);
+            self.write(data - size_of::<u64>(), class as u64);
+        }
+        Ok((data, block_size - data_offset))
+    }
+
+    /// Returns the block of the allocation at `offset` to the free list for its size class
+    ///
+    /// # Safety
+    /// `offset` must have been returned by [`SizeClassHeap::allocate`] and not deallocated yet
+    pub(crate) unsafe fn deallocate(&self, offset: usize) {
+        unsafe {
+            let (start, size) = self.block(offset);
+            let free = &raw mut (*self.header.as_ptr()).free[size.trailing_zeros() as usize];
+            self.write(start, *free);
+            *free = start as u64;
+        }
+    }
+
+    /// Returns the new size of the allocation at `offset` if it can be resized to `new_layout` without moving it
+    ///
+    /// # Safety
+    /// `offset` must have been returned by [`SizeClassHeap::allocate`] and not deallocated yet
+    pub(crate) unsafe fn resize_in_place(
+        &self,
+        offset: usize,
+        new_layout: Layout,
+    ) -> Option<usize> {
+        let (start, size) = unsafe { self.block(offset) };
+        let block_end = start + size;
+        (new_layout.align() <= self.max_align
+            && offset.is_multiple_of(new_layout.align())
+            && offset + new_layout.size() <= block_end)
+            .then_some(block_end - offset)
+    }
+
+    /// Returns whether the state of the heap is consistent, so that it is safe to allocate from it
+    ///
+    /// `start` is where the heap started allocating from, and `length` is how much memory there is
+    pub(crate) fn validate(&self, start: usize, length: usize) -> bool {
+        let header = self.header.as_ptr();
+        let top = unsafe { (*header).top };
+        if top < start as u64 || top > length as u64 {
+            return false;
+        }
+
+        // every block is at least `MIN_BLOCK_SIZE`, so a longer list must have a cycle
+        let mut remaining = top as usize / MIN_BLOCK_SIZE;
+        let mut blocks = Vec::new();
+        for class in 0..CLASSES {
+            let mut block = unsafe { (*header).free[class] };
+            while block != NONE {
+                let block_size = 1u64 << class;
+                if remaining == 0
+                    || block_size < MIN_BLOCK_SIZE as u64
+                    || block < start as u64
+                    || block > top.saturating_sub(block_size)
+                    || !block.is_multiple_of(block_size.min(self.max_align as u64))
+                {
+                    return false;
+                }
+                remaining -= 1;
+                blocks.push((block, block + block_size));
+                block = unsafe { self.read(block as usize) };
+            }
+        }
+
+        // a block that is in two lists, or two blocks that overlap, would be given to two allocations at once
+        blocks.sort_unstable();
+        blocks.windows(2).all(|pair| pair[0].1 <= pair[1].0)
+    }
+
+    /// Returns whether any free block overlaps `start..end`,
+    /// this can only be used after [`SizeClassHeap::validate`] has returned `true`
+    fn overlaps_free_block(&self, start: usize, end: usize) -> bool {
+        let header = self.header.as_ptr();
+        (0..CLASSES).any(|class| {
+            let mut block = unsafe { (*header).free[class] } as usize;
+            while block != NONE as usize {
+                if block < end && start < block + (1 << class) {
+                    return true;
+                }
+                block = unsafe { self.read(block) } as usize;
+            }
+            false
+        })
+    }
+
+    /// Returns whether `offset` looks like an allocation that fits `layout`
+    ///
+    /// This cant detect every invalid offset, but if it returns `true` then the allocation is inside the heap and doesnt overlap a free block,
+    /// this can only be used after [`SizeClassHeap::validate`] has returned `true`
+    pub(crate) fn contains(&self, start: usize, offset: usize, layout: Layout) -> bool {
+        let top = unsafe { (*self.header.as_ptr()).top } as usize;
+        if offset < start + BLOCK_HEADER_SIZE
+            || offset > top
+            || !offset.is_multiple_of(size_of::<u64>())
+            || !offset.is_multiple_of(layout.align())
+        {
+            return false;
+        }
+
+        let (block, class) = unsafe {
+            (
+                self.read(offset - BLOCK_HEADER_SIZE),
+                self.read(offset - size_of::<u64>()),
+            )
+        };
+        class < CLASSES as u64
+            && block >= start as u64
+            && block < offset as u64
+            && 1usize
+                .checked_shl(class as u32)
+                .and_then(|size| (block as usize).checked_add(size))
+                .is_some_and(|end| {
+                    end <= top
+                        && offset + layout.size() <= end
+                        && !self.overlaps_free_block(block as usize, end)
+                })
+    }
+}
@@ -19,6 +19,84 @@ pub struct SlotStorage<'a> {
 
 unsafe impl Send for SlotStorage<'_> {}
 
+impl<'a> SlotStorage<'a> {
+    /// Constructs a [`SlotStorage`] that allocates from an uninitialised `buffer`
+    ///
+    /// ```
+    /// use core::mem::MaybeUninit;
+    /// use storage_api::{SlotStorage, Vec};
+    /// # use storage_api::StorageAllocError;
+    ///
+    /// # fn main() -> Result<(), StorageAllocError> {
+    ///
+    /// let mut buffer = [MaybeUninit::uninit(); 16];
+    /// let mut v = Vec::<u32, SlotStorage<'_>>::new_in(SlotStorage::from_uninit(&mut buffer))?;
+    /// v.extend_from_slice(&[1, 2, 3])?;
+    /// assert_eq!(&*v, &[1, 2, 3]);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_uninit(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        Self {
+            storage: unsafe {
+                &*(core::ptr::from_mut(buffer) as *const [UnsafeCell<MaybeUninit<u8>>])
+            },
+        }
+    }
+
+    /// Constructs a [`SlotStorage`] that allocates from the bytes of `buffer`
+    ///
+    /// Allocations with an alignment up to `align_of::<T>()` will always succeed as long as they fit in the buffer
+    ///
+    /// ```
+    /// use core::mem::MaybeUninit;
+    /// use storage_api::{SlotStorage, Vec};
+    /// # use storage_api::StorageAllocError;
+    ///
+    /// # fn main() -> Result<(), StorageAllocError> {
+    ///
+    /// let mut buffer = [MaybeUninit::<u64>::uninit(); 4];
+    /// let storage = SlotStorage::new_aligned(&mut buffer);
+    /// assert_eq!(storage.len(), 32);
+    ///
+    /// let mut v = Vec::<u64, SlotStorage<'_>>::new_in(storage)?;
+    /// v.extend_from_slice(&[1, 2, 3, 4])?;
+    /// assert_eq!(v.capacity(), 4);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_aligned<T>(buffer: &'a mut [MaybeUninit<T>]) -> Self {
+        let length = size_of_val(buffer);
+        Self::from_uninit(unsafe {
+            core::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast(), length)
+        })
+    }
+
+    /// Returns the length of the buffer in bytes
+    pub fn len(&self) -> usize {
+        self.storage.len()
+    }
+
+    /// Returns `true` if the buffer has a length of 0
+    pub fn is_empty(&self) -> bool {
+        self.storage.is_empty()
+    }
+
+    /// Returns the buffer that this [`SlotStorage`] was allocating from
+    ///
+    /// Any data written by allocations is left in the buffer
+    pub fn into_inner(self) -> &'a mut [MaybeUninit<u8>] {
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                UnsafeCell::raw_get(self.storage.as_ptr()),
+                self.storage.len(),
+            )
+        }
+    }
+}
+
 unsafe impl Storage for SlotStorage<'_> {
     type Handle = SlotStorageHandle;
 
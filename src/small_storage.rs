@@ -0,0 +1,197 @@
+use crate::{Global, Storage, StorageAllocError, StorageHandle};
+use core::{alloc::Layout, ptr::NonNull};
+
+/// The [`StorageHandle`] for [`SmallStorage`],
+/// this remembers which [`Storage`] the allocation is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SmallStorageHandle<I, F> {
+    /// The allocation is in the inline [`Storage`]
+    Inline(I),
+    /// The allocation is in the fallback [`Storage`]
+    Fallback(F),
+}
+
+impl<I: StorageHandle, F: StorageHandle> StorageHandle for SmallStorageHandle<I, F> {}
+
+/// A [`Storage`] that allocates from an inline [`Storage`] (usually an [`InlineStorage`](crate::InlineStorage)) until the allocation gets too big,
+/// and then moves it to a fallback [`Storage`]
+///
+/// This turns a [`Vec`](crate::Vec) into a "small vector" that only allocates once it has more elements than fit inline
+///
+/// ```
+/// use storage_api::{InlineStorage, SmallStorage, Vec, storages::SmallStorageHandle};
+/// # use storage_api::StorageAllocError;
+///
+/// type S = SmallStorage<InlineStorage<[i32; 4]>>; // room for 4 `i32`s before it uses `Global`
+///
+/// fn is_inline(v: Vec<i32, S>) -> (bool, Vec<i32, S>) {
+///     let (storage, handle, length, capacity) = v.into_raw_parts();
+///     let inline = matches!(handle, SmallStorageHandle::Inline(_));
+///     (inline, unsafe { Vec::from_raw_parts(storage, handle, length, capacity) })
+/// }
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let mut v = Vec::<i32, S>::new()?;
+/// v.extend_from_slice(&[1, 2, 3, 4])?;
+/// let (inline, mut v) = is_inline(v);
+/// assert!(inline);
+///
+/// v.extend_from_slice(&[5, 6, 7, 8])?; // this moves the elements to `Global`
+/// let (inline, mut v) = is_inline(v);
+/// assert!(!inline);
+///
+/// for _ in 0..4 {
+///     v.remove(0);
+/// }
+/// v.shrink_to_fit()?; // this moves the elements back inline
+/// let (inline, v) = is_inline(v);
+/// assert!(inline);
+/// assert_eq!(&*v, &[5, 6, 7, 8]);
+///
+/// # Ok(())
+/// # }
+/// ```
+pub struct SmallStorage<I, F = Global> {
+    inline: I,
+    fallback: F,
+    shrink_inline: bool,
+}
+
+impl<I, F> SmallStorage<I, F> {
+    /// Constructs a new [`SmallStorage`] from its inline and fallback [`Storage`]s
+    ///
+    /// Shrinking an allocation in the fallback [`Storage`] will move it back inline if it fits, see [`SmallStorage::set_shrink_inline`]
+    pub const fn new(inline: I, fallback: F) -> Self {
+        Self {
+            inline,
+            fallback,
+            shrink_inline: true,
+        }
+    }
+
+    /// Returns whether shrinking an allocation in the fallback [`Storage`] will move it back inline if it fits
+    pub fn shrink_inline(&self) -> bool {
+        self.shrink_inline
+    }
+
+    /// Sets whether shrinking an allocation in the fallback [`Storage`] will move it back inline if it fits
+    pub fn set_shrink_inline(&mut self, shrink_inline: bool) {
+        self.shrink_inline = shrink_inline;
+    }
+
+    /// Returns a reference to the inline [`Storage`]
+    pub fn inline(&self) -> &I {
+        &self.inline
+    }
+
+    /// Returns a reference to the fallback [`Storage`]
+    pub fn fallback(&self) -> &F {
+        &self.fallback
+    }
+}
+
+unsafe impl<I: Storage, F: Storage> Storage for SmallStorage<I, F> {
+    type Handle = SmallStorageHandle<I::Handle, F::Handle>;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        match handle {
+            SmallStorageHandle::Inline(handle) => unsafe { self.inline.resolve(handle) },
+            SmallStorageHandle::Fallback(handle) => unsafe { self.fallback.resolve(handle) },
+        }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        match self.inline.allocate(layout) {
+            Ok((handle, size)) => Ok((SmallStorageHandle::Inline(handle), size)),
+            Err(StorageAllocError) => {
+                let (handle, size) = self.fallback.allocate(layout)?;
+                Ok((SmallStorageHandle::Fallback(handle), size))
+            }
+        }
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        match handle {
+            SmallStorageHandle::Inline(handle) => unsafe { self.inline.deallocate(layout, handle) },
+            SmallStorageHandle::Fallback(handle) => unsafe {
+                self.fallback.deallocate(layout, handle)
+            },
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        match handle {
+            SmallStorageHandle::Inline(handle) => {
+                if let Ok((handle, size)) =
+                    unsafe { self.inline.grow(old_layout, new_layout, handle) }
+                {
+                    return Ok((SmallStorageHandle::Inline(handle), size));
+                }
+
+                let (new_handle, size) = self.fallback.allocate(new_layout)?;
+                unsafe {
+                    self.fallback
+                        .resolve(new_handle)
+                        .cast::<u8>()
+                        .copy_from_nonoverlapping(
+                            self.inline.resolve(handle).cast(),
+                            old_layout.size(),
+                        );
+                    self.inline.deallocate(old_layout, handle);
+                }
+                Ok((SmallStorageHandle::Fallback(new_handle), size))
+            }
+            SmallStorageHandle::Fallback(handle) => {
+                let (handle, size) = unsafe { self.fallback.grow(old_layout, new_layout, handle)? };
+                Ok((SmallStorageHandle::Fallback(handle), size))
+            }
+        }
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        match handle {
+            SmallStorageHandle::Inline(handle) => {
+                let (handle, size) = unsafe { self.inline.shrink(old_layout, new_layout, handle)? };
+                Ok((SmallStorageHandle::Inline(handle), size))
+            }
+            SmallStorageHandle::Fallback(handle) => {
+                if self.shrink_inline
+                    && let Ok((new_handle, size)) = self.inline.allocate(new_layout)
+                {
+                    unsafe {
+                        self.inline
+                            .resolve(new_handle)
+                            .cast::<u8>()
+                            .copy_from_nonoverlapping(
+                                self.fallback.resolve(handle).cast(),
+                                new_layout.size(),
+                            );
+                        self.fallback.deallocate(old_layout, handle);
+                    }
+                    return Ok((SmallStorageHandle::Inline(new_handle), size));
+                }
+
+                let (handle, size) =
+                    unsafe { self.fallback.shrink(old_layout, new_layout, handle)? };
+                Ok((SmallStorageHandle::Fallback(handle), size))
+            }
+        }
+    }
+}
+
+impl<I: Default, F: Default> Default for SmallStorage<I, F> {
+    fn default() -> Self {
+        Self::new(Default::default(), Default::default())
+    }
+}
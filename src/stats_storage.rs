@@ -0,0 +1,297 @@
+extern crate alloc;
+
+use crate::{
+    MultipleStorage, PointerStorage, ShareableStorage, StableStorage, Storage, StorageAllocError,
+    StorageHandle,
+    locked_storage::{Guard, RawLock, SpinLock},
+};
+use alloc::{collections::BTreeMap, sync::Arc};
+use core::{
+    alloc::Layout,
+    cell::UnsafeCell,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A snapshot of the counters of a [`StatsStorage`], returned by [`StatsStorage::stats`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StorageStats {
+    /// The number of successful calls to [`Storage::allocate`]
+    pub allocations: usize,
+    /// The number of calls to [`Storage::deallocate`]
+    pub deallocations: usize,
+    /// The number of successful calls to [`Storage::grow`]
+    pub grows: usize,
+    /// The number of successful calls to [`Storage::shrink`]
+    pub shrinks: usize,
+    /// The number of calls to [`Storage::allocate`]/[`Storage::grow`]/[`Storage::shrink`] that failed
+    pub failures: usize,
+    /// The number of bytes currently allocated
+    ///
+    /// This counts the requested sizes, even if the inner [`Storage`] returned more and a collection is using the extra space
+    pub live_bytes: usize,
+    /// The greatest value that [`StorageStats::live_bytes`] has had
+    pub peak_bytes: usize,
+}
+
+#[derive(Default)]
+struct Counters {
+    allocations: AtomicUsize,
+    deallocations: AtomicUsize,
+    grows: AtomicUsize,
+    shrinks: AtomicUsize,
+    failures: AtomicUsize,
+    live_bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+}
+
+impl Counters {
+    fn add_live(&self, size: usize) {
+        let live = self.live_bytes.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak_bytes.fetch_max(live, Ordering::Relaxed);
+    }
+
+    fn sub_live(&self, size: usize) {
+        self.live_bytes.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+/// The size counted in [`StorageStats::live_bytes`] for every live allocation,
+/// this is needed because the size passed back when freeing an allocation can be anything up to the size the inner [`Storage`] returned
+struct Sizes<H> {
+    lock: SpinLock,
+    /// the number of live allocations with each handle and size, there can be more than one if clones of the inner [`Storage`] give out the same handles
+    sizes: UnsafeCell<BTreeMap<(H, usize), usize>>,
+}
+
+unsafe impl<H: Send> Sync for Sizes<H> {}
+
+impl<H> Default for Sizes<H> {
+    fn default() -> Self {
+        Self {
+            lock: SpinLock::UNLOCKED,
+            sizes: UnsafeCell::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl<H: StorageHandle> Sizes<H> {
+    fn with<R>(&self, f: impl FnOnce(&mut BTreeMap<(H, usize), usize>) -> R) -> R {
+        let _guard = Guard::new(&self.lock);
+        f(unsafe { &mut *self.sizes.get() })
+    }
+
+    fn insert(&self, handle: H, size: usize) {
+        self.with(|sizes| *sizes.entry((handle, size)).or_default() += 1);
+    }
+
+    /// Returns the size that was counted for `handle`
+    fn remove(&self, handle: H) -> usize {
+        self.with(|sizes| {
+            let Some((&key, count)) = sizes.range_mut((handle, 0)..=(handle, usize::MAX)).next()
+            else {
+                return 0;
+            };
+            *count -= 1;
+            if *count == 0 {
+                sizes.remove(&key);
+            }
+            key.1
+        })
+    }
+}
+
+/// A wrapper around a [`Storage`] that counts allocations and how many bytes are in use
+///
+/// Cloning a [`StatsStorage`] clones the inner [`Storage`] but keeps the same counters,
+/// so a copy can be kept around to read the counters of a [`StatsStorage`] that has been moved into a collection
+///
+/// The sizes returned by the inner [`Storage`] are passed on unchanged, and the size of every live allocation is kept in a table allocated with the global allocator,
+/// so a [`StatsStorage`] cant be used in the `#[global_allocator]`
+///
+/// ```
+/// use storage_api::{Global, StatsStorage, Vec};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let storage = StatsStorage::new(Global);
+/// let mut v = Vec::<u32, _>::with_capacity_in(4, storage.clone())?;
+/// v.extend_from_slice(&[1, 2, 3, 4])?;
+/// assert_eq!(storage.stats().live_bytes, 16);
+///
+/// v.reserve_exact(4)?;
+/// drop(v);
+///
+/// let stats = storage.stats();
+/// assert_eq!(stats.allocations, 1);
+/// assert_eq!(stats.grows, 1);
+/// assert_eq!(stats.deallocations, 1);
+/// assert_eq!(stats.live_bytes, 0);
+/// assert_eq!(stats.peak_bytes, 32);
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Only the requested sizes are counted, even if the inner [`Storage`] returns more space
+/// ```
+/// use storage_api::{PoolStorage, StatsStorage, Vec};
+///
+/// let storage = StatsStorage::new(PoolStorage::<[u8; 64], 4>::new());
+/// let mut v = Vec::<u8, _>::with_capacity_in(1, &storage).unwrap();
+/// assert_eq!((v.capacity(), storage.stats().live_bytes), (64, 1));
+///
+/// v.extend_from_slice(&[1; 64]).unwrap();
+/// drop(v);
+/// assert_eq!(storage.stats().live_bytes, 0);
+/// ```
+pub struct StatsStorage<S: Storage> {
+    storage: S,
+    counters: Arc<Counters>,
+    sizes: Arc<Sizes<S::Handle>>,
+}
+
+impl<S: Storage> StatsStorage<S> {
+    /// Constructs a new [`StatsStorage`] wrapping `storage`, with all the counters at 0
+    pub fn new(storage: S) -> Self {
+        Self {
+            storage,
+            counters: Arc::default(),
+            sizes: Arc::default(),
+        }
+    }
+
+    /// Returns a snapshot of the counters
+    pub fn stats(&self) -> StorageStats {
+        let counters = &*self.counters;
+        StorageStats {
+            allocations: counters.allocations.load(Ordering::Relaxed),
+            deallocations: counters.deallocations.load(Ordering::Relaxed),
+            grows: counters.grows.load(Ordering::Relaxed),
+            shrinks: counters.shrinks.load(Ordering::Relaxed),
+            failures: counters.failures.load(Ordering::Relaxed),
+            live_bytes: counters.live_bytes.load(Ordering::Relaxed),
+            peak_bytes: counters.peak_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns a reference to the inner [`Storage`]
+    pub fn inner(&self) -> &S {
+        &self.storage
+    }
+
+    /// Returns the inner [`Storage`]
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    fn record(
+        &self,
+        counter: &AtomicUsize,
+        old_handle: Option<S::Handle>,
+        new_size: usize,
+        result: Result<(S::Handle, usize), StorageAllocError>,
+    ) -> Result<(S::Handle, usize), StorageAllocError> {
+        match result {
+            Ok((handle, size)) => {
+                counter.fetch_add(1, Ordering::Relaxed);
+                if let Some(old_handle) = old_handle {
+                    self.counters.sub_live(self.sizes.remove(old_handle));
+                }
+                self.sizes.insert(handle, new_size);
+                self.counters.add_live(new_size);
+                Ok((handle, size))
+            }
+            Err(error) => {
+                self.counters.failures.fetch_add(1, Ordering::Relaxed);
+                Err(error)
+            }
+        }
+    }
+}
+
+unsafe impl<S: Storage> Storage for StatsStorage<S> {
+    type Handle = S::Handle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { self.storage.resolve(handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        self.record(
+            &self.counters.allocations,
+            None,
+            layout.size(),
+            self.storage.allocate(layout),
+        )
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        unsafe { self.storage.deallocate(layout, handle) };
+        self.counters.deallocations.fetch_add(1, Ordering::Relaxed);
+        self.counters.sub_live(self.sizes.remove(handle));
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        self.record(
+            &self.counters.grows,
+            Some(handle),
+            new_layout.size(),
+            unsafe { self.storage.grow(old_layout, new_layout, handle) },
+        )
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        self.record(
+            &self.counters.shrinks,
+            Some(handle),
+            new_layout.size(),
+            unsafe { self.storage.shrink(old_layout, new_layout, handle) },
+        )
+    }
+}
+
+unsafe impl<S: ShareableStorage> ShareableStorage for StatsStorage<S> {
+    unsafe fn make_shared_copy(&self) -> Self {
+        Self {
+            storage: unsafe { self.storage.make_shared_copy() },
+            counters: self.counters.clone(),
+            sizes: self.sizes.clone(),
+        }
+    }
+}
+
+unsafe impl<S: MultipleStorage> MultipleStorage for StatsStorage<S> {}
+unsafe impl<S: StableStorage> StableStorage for StatsStorage<S> {}
+unsafe impl<S: PointerStorage> PointerStorage for StatsStorage<S> {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        unsafe { self.storage.handle_from_ptr(ptr) }
+    }
+}
+
+impl<S: Storage + Clone> Clone for StatsStorage<S> {
+    fn clone(&self) -> Self {
+        Self {
+            storage: self.storage.clone(),
+            counters: self.counters.clone(),
+            sizes: self.sizes.clone(),
+        }
+    }
+}
+
+impl<S: Storage + Default> Default for StatsStorage<S> {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
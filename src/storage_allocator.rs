@@ -0,0 +1,169 @@
+use crate::{Global, MultipleStorage, PointerStorage, ShareableStorage, StableStorage};
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    ptr::NonNull,
+};
+
+/// An adapter that lets a [`PointerStorage`] be used as an [`Allocator`](core::alloc::Allocator) (with the `nightly` feature) or a [`GlobalAlloc`]
+///
+/// Zero sized allocations are handled by the adapter and never reach the [`Storage`](crate::Storage)
+///
+/// To use it as the `#[global_allocator]`, the [`Storage`](crate::Storage) must be [`Sync`] (for example by wrapping it in a [`LockedStorage`](crate::LockedStorage)),
+/// and must not allocate from the global allocator itself
+///
+/// Every clone of an allocator must be able to free the allocations of the others, so this is only [`Clone`] when the [`Storage`](crate::Storage) is a [`ShareableStorage`]
+///
+/// ```
+/// #![feature(allocator_api)]
+///
+/// use core::mem::MaybeUninit;
+/// use storage_api::{FreeListStorage, StorageAllocator};
+///
+/// let mut buffer = [MaybeUninit::uninit(); 1024];
+/// let allocator = StorageAllocator::new(FreeListStorage::from_uninit(&mut buffer));
+///
+/// let mut v = Vec::new_in(&allocator);
+/// v.extend([1, 2, 3]);
+/// let b = Box::new_in("hello", &allocator);
+/// assert_eq!((&*v, *b), (&[1, 2, 3][..], "hello"));
+/// ```
+#[derive(Default)]
+pub struct StorageAllocator<S> {
+    storage: S,
+}
+
+impl<S: ShareableStorage> Clone for StorageAllocator<S> {
+    fn clone(&self) -> Self {
+        // the copy acts the same as `self`, which is what `Allocator` requires of clones
+        Self::new(unsafe { self.storage.make_shared_copy() })
+    }
+}
+
+impl Copy for StorageAllocator<Global> {}
+impl<T: MultipleStorage + ?Sized> Copy for StorageAllocator<&T> {}
+
+impl<S> StorageAllocator<S> {
+    /// Constructs a new [`StorageAllocator`] that allocates from `storage`
+    pub const fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Returns a reference to the [`Storage`](crate::Storage)
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// Returns the [`Storage`](crate::Storage)
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+}
+
+impl<S: PointerStorage + MultipleStorage + StableStorage> StorageAllocator<S> {
+    fn dangling(layout: Layout) -> NonNull<u8> {
+        unsafe { NonNull::new_unchecked(core::ptr::without_provenance_mut(layout.align())) }
+    }
+
+    fn alloc_ptr(&self, layout: Layout) -> Option<(NonNull<u8>, usize)> {
+        if layout.size() == 0 {
+            return Some((Self::dangling(layout), 0));
+        }
+
+        let (handle, size) = self.storage.allocate(layout).ok()?;
+        Some((unsafe { self.storage.resolve(handle) }.cast(), size))
+    }
+
+    unsafe fn dealloc_ptr(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            unsafe {
+                let handle = self.storage.handle_from_ptr(ptr.cast());
+                self.storage.deallocate(layout, handle);
+            }
+        }
+    }
+
+    unsafe fn realloc_ptr(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Option<(NonNull<u8>, usize)> {
+        if old_layout.size() == 0 {
+            return self.alloc_ptr(new_layout);
+        }
+        if new_layout.size() == 0 {
+            unsafe { self.dealloc_ptr(ptr, old_layout) };
+            return Some((Self::dangling(new_layout), 0));
+        }
+
+        unsafe {
+            let handle = self.storage.handle_from_ptr(ptr.cast());
+            let (handle, size) = if new_layout.size() >= old_layout.size() {
+                self.storage.grow(old_layout, new_layout, handle)
+            } else {
+                self.storage.shrink(old_layout, new_layout, handle)
+            }
+            .ok()?;
+            Some((self.storage.resolve(handle).cast(), size))
+        }
+    }
+}
+
+#[cfg(feature = "nightly")]
+unsafe impl<S: PointerStorage + MultipleStorage + StableStorage> core::alloc::Allocator
+    for StorageAllocator<S>
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+        let (ptr, size) = self.alloc_ptr(layout).ok_or(core::alloc::AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, size))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { self.dealloc_ptr(ptr, layout) }
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+        let (ptr, size) = unsafe { self.realloc_ptr(ptr, old_layout, new_layout) }
+            .ok_or(core::alloc::AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+        let (ptr, size) = unsafe { self.realloc_ptr(ptr, old_layout, new_layout) }
+            .ok_or(core::alloc::AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, size))
+    }
+}
+
+unsafe impl<S: PointerStorage + MultipleStorage + StableStorage> GlobalAlloc
+    for StorageAllocator<S>
+{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match self.alloc_ptr(layout) {
+            Some((ptr, _)) => ptr.as_ptr(),
+            None => core::ptr::null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { self.dealloc_ptr(NonNull::new_unchecked(ptr), layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_layout = unsafe { Layout::from_size_align_unchecked(new_size, layout.align()) };
+        match unsafe { self.realloc_ptr(NonNull::new_unchecked(ptr), layout, new_layout) } {
+            Some((ptr, _)) => ptr.as_ptr(),
+            None => core::ptr::null_mut(),
+        }
+    }
+}
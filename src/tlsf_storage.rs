@@ -0,0 +1,547 @@
+use crate::{
+    MultipleStorage, PointerStorage, StableStorage, Storage, StorageAllocError, StorageHandle,
+};
+use core::{alloc::Layout, cell::Cell, marker::PhantomData, mem::MaybeUninit, ptr::NonNull};
+
+/// The [`StorageHandle`] for [`TlsfStorage`],
+/// this is the offset of the allocation from the start of the region
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TlsfStorageHandle {
+    offset: usize,
+}
+
+impl StorageHandle for TlsfStorageHandle {}
+
+/// Statistics about the free memory in a [`TlsfStorage`], returned by [`TlsfStorage::stats`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TlsfStats {
+    /// The number of bytes in blocks that are currently allocated, including block headers
+    pub used_bytes: usize,
+    /// The number of bytes in free blocks, including block headers
+    pub free_bytes: usize,
+    /// The number of free blocks
+    pub free_blocks: usize,
+    /// The size of the largest free block, including its header
+    pub largest_free_block: usize,
+    /// The largest allocation size that is guaranteed to succeed (as long as its alignment is not greater than `align_of::<usize>()`)
+    ///
+    /// This may be a bit less than the size of the largest free block, because blocks are searched for by rounding up to the next size class
+    pub largest_free_allocation: usize,
+}
+
+impl TlsfStats {
+    /// Returns how fragmented the free memory is, from `0.0` when all free memory is in one block to almost `1.0` when it is split into many small blocks
+    pub fn fragmentation(&self) -> f32 {
+        if self.free_bytes == 0 {
+            0.0
+        } else {
+            1.0 - self.largest_free_block as f32 / self.free_bytes as f32
+        }
+    }
+}
+
+/// Stored at the start of every block
+#[derive(Clone, Copy)]
+struct BlockHeader {
+    previous_physical: usize,
+    /// the size of the block including this header, the lowest bit is set if the block is free
+    size: usize,
+}
+
+/// Stored after the [`BlockHeader`] of every free block
+#[derive(Clone, Copy)]
+struct FreeLinks {
+    previous: usize,
+    next: usize,
+}
+
+const NONE: usize = usize::MAX;
+const FREE: usize = 1;
+const GRANULE: usize = align_of::<usize>();
+const GRANULE_LOG: u32 = GRANULE.trailing_zeros();
+const HEADER_SIZE: usize = size_of::<BlockHeader>();
+const MIN_BLOCK_SIZE: usize = HEADER_SIZE + size_of::<FreeLinks>();
+
+const SL_LOG: u32 = 4;
+const SL_COUNT: usize = 1 << SL_LOG;
+const FL_SHIFT: u32 = SL_LOG + GRANULE_LOG;
+const SMALL_BLOCK_SIZE: usize = 1 << FL_SHIFT;
+const FL_COUNT: usize = (usize::BITS - FL_SHIFT + 1) as usize;
+
+/// The segregated free lists, this is stored at the start of the buffer
+struct Control {
+    fl_bitmap: Cell<usize>,
+    sl_bitmaps: [Cell<usize>; FL_COUNT],
+    heads: [[Cell<usize>; SL_COUNT]; FL_COUNT],
+}
+
+/// Returns the free list that blocks of `size` are stored in
+fn mapping(size: usize) -> (usize, usize) {
+    if size < SMALL_BLOCK_SIZE {
+        (0, size >> GRANULE_LOG)
+    } else {
+        let log = size.ilog2();
+        let sl = (size >> (log - SL_LOG)) - SL_COUNT;
+        ((log - FL_SHIFT + 1) as usize, sl)
+    }
+}
+
+/// Returns the smallest block size in the same free list as `size`
+fn class_floor(size: usize) -> usize {
+    if size < SMALL_BLOCK_SIZE {
+        size
+    } else {
+        size & !((1 << (size.ilog2() - SL_LOG)) - 1)
+    }
+}
+
+/// Returns the first free list where every block is at least `size` bytes
+fn mapping_search(size: usize) -> Option<(usize, usize)> {
+    if size < SMALL_BLOCK_SIZE {
+        Some(mapping(size))
+    } else {
+        let round = (1 << (size.ilog2() - SL_LOG)) - 1;
+        Some(mapping(size.checked_add(round)? & !round))
+    }
+}
+
+const fn align_up(value: usize, align: usize) -> Option<usize> {
+    match value.checked_add(align - 1) {
+        Some(value) => Some(value & !(align - 1)),
+        None => None,
+    }
+}
+
+/// A two level segregated fit allocator, where allocating and deallocating both take constant time
+///
+/// Free blocks are kept in lists of similar sizes that can be found using bit scans,
+/// so this is suitable for real-time code where allocations need bounded latency.
+/// Allocations are grown/shrunk in place when the block after them is free
+///
+/// ```
+/// use core::mem::MaybeUninit;
+/// use storage_api::{TlsfStorage, Vec, String};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let mut buffer = [MaybeUninit::uninit(); 16384];
+/// let storage = TlsfStorage::from_uninit(&mut buffer);
+/// let headroom = storage.stats().largest_free_block;
+///
+/// let mut v = Vec::<u32, _>::new_in(&storage)?;
+/// let s = String::from_str_in("hello", &storage)?;
+/// v.extend_from_slice(&[1, 2, 3])?;
+/// assert!(storage.stats().largest_free_block < headroom);
+///
+/// drop((v, s));
+/// let stats = storage.stats();
+/// assert_eq!(stats.largest_free_block, headroom); // all the free blocks were merged again
+/// assert_eq!(stats.fragmentation(), 0.0);
+///
+/// # Ok(())
+/// # }
+/// ```
+pub struct TlsfStorage<'a> {
+    /// [`None`] if the buffer was too small to hold the free lists, then every allocation fails
+    control: Option<NonNull<Control>>,
+    base: NonNull<u8>,
+    length: usize,
+    _buffer: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+unsafe impl Send for TlsfStorage<'_> {}
+
+impl<'a> TlsfStorage<'a> {
+    /// Constructs a [`TlsfStorage`] that allocates from an uninitialised `buffer`
+    ///
+    /// The start of the buffer is used to store the free lists, which takes about 8 kilobytes on 64 bit targets,
+    /// if `buffer` is too small to hold them then every allocation fails
+    ///
+    /// ```
+    /// use core::mem::MaybeUninit;
+    /// use storage_api::{Box, TlsfStorage};
+    ///
+    /// let mut buffer = [MaybeUninit::uninit(); 64];
+    /// let storage = TlsfStorage::from_uninit(&mut buffer);
+    /// assert!(Box::new_in(1, &storage).is_err());
+    /// ```
+    pub fn from_uninit(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        let start = NonNull::from(&mut *buffer).cast::<u8>();
+        let padding = start.align_offset(align_of::<Control>());
+        let Some(length) = buffer
+            .len()
+            .checked_sub(padding)
+            .and_then(|length| length.checked_sub(size_of::<Control>()))
+        else {
+            return Self {
+                control: None,
+                base: start,
+                length: 0,
+                _buffer: PhantomData,
+            };
+        };
+        let length = length & !(GRANULE - 1);
+
+        unsafe {
+            let control = start.add(padding).cast::<Control>();
+            control.write(Control {
+                fl_bitmap: Cell::new(0),
+                sl_bitmaps: [const { Cell::new(0) }; FL_COUNT],
+                heads: [const { [const { Cell::new(NONE) }; SL_COUNT] }; FL_COUNT],
+            });
+
+            let this = Self {
+                control: Some(control),
+                base: control.add(1).cast(),
+                length,
+                _buffer: PhantomData,
+            };
+            if length >= MIN_BLOCK_SIZE {
+                this.write_header(
+                    0,
+                    BlockHeader {
+                        previous_physical: NONE,
+                        size: length,
+                    },
+                );
+                this.insert(0);
+            }
+            this
+        }
+    }
+
+    /// Walks every block to collect statistics about the free memory
+    ///
+    /// Unlike the other methods this takes time proportional to the number of blocks,
+    /// so it is meant to be used for checking headroom at startup or in tests
+    pub fn stats(&self) -> TlsfStats {
+        let mut stats = TlsfStats::default();
+        let mut offset = 0;
+        while offset < self.length && self.length >= MIN_BLOCK_SIZE {
+            let size = self.size(offset);
+            if self.is_free(offset) {
+                stats.free_bytes += size;
+                stats.free_blocks += 1;
+                stats.largest_free_block = stats.largest_free_block.max(size);
+            } else {
+                stats.used_bytes += size;
+            }
+            offset += size;
+        }
+        stats.largest_free_allocation =
+            class_floor(stats.largest_free_block).saturating_sub(HEADER_SIZE);
+        stats
+    }
+
+    /// Returns the free lists, this must only be called once there is a block, which means the buffer was big enough to hold them
+    fn control(&self) -> &Control {
+        unsafe { self.control.unwrap_unchecked().as_ref() }
+    }
+
+    fn header(&self, offset: usize) -> NonNull<BlockHeader> {
+        unsafe { self.base.add(offset).cast() }
+    }
+
+    fn read_header(&self, offset: usize) -> BlockHeader {
+        unsafe { self.header(offset).read() }
+    }
+
+    unsafe fn write_header(&self, offset: usize, header: BlockHeader) {
+        unsafe { self.header(offset).write(header) }
+    }
+
+    fn links(&self, offset: usize) -> NonNull<FreeLinks> {
+        unsafe { self.base.add(offset + HEADER_SIZE).cast() }
+    }
+
+    fn size(&self, offset: usize) -> usize {
+        self.read_header(offset).size & !FREE
+    }
+
+    fn is_free(&self, offset: usize) -> bool {
+        self.read_header(offset).size & FREE != 0
+    }
+
+    unsafe fn set_free(&self, offset: usize, free: bool) {
+        let mut header = self.read_header(offset);
+        header.size = (header.size & !FREE) | if free { FREE } else { 0 };
+        unsafe { self.write_header(offset, header) };
+    }
+
+    fn next_physical(&self, offset: usize) -> Option<usize> {
+        Some(offset + self.size(offset)).filter(|&next| next < self.length)
+    }
+
+    /// Sets the size of the (used) block at `offset`, and updates the block after it to point back to it
+    unsafe fn resize_block(&self, offset: usize, size: usize) {
+        unsafe {
+            let header = self.read_header(offset);
+            self.write_header(
+                offset,
+                BlockHeader {
+                    previous_physical: header.previous_physical,
+                    size,
+                },
+            );
+            if let Some(next) = self.next_physical(offset) {
+                let mut next_header = self.read_header(next);
+                next_header.previous_physical = offset;
+                self.write_header(next, next_header);
+            }
+        }
+    }
+
+    /// Splits the used block at `offset` so it is `size` bytes, returning the offset of the (used) block after it
+    unsafe fn split(&self, offset: usize, size: usize) -> usize {
+        let header = self.read_header(offset);
+        unsafe {
+            self.write_header(
+                offset,
+                BlockHeader {
+                    previous_physical: header.previous_physical,
+                    size,
+                },
+            );
+            self.write_header(
+                offset + size,
+                BlockHeader {
+                    previous_physical: offset,
+                    size: 0,
+                },
+            );
+            self.resize_block(offset + size, (header.size & !FREE) - size);
+        }
+        offset + size
+    }
+
+    /// Adds the block at `offset` to the free lists
+    unsafe fn insert(&self, offset: usize) {
+        let (fl, sl) = mapping(self.size(offset));
+        let control = self.control();
+        let head = control.heads[fl][sl].replace(offset);
+        unsafe {
+            self.set_free(offset, true);
+            self.links(offset).write(FreeLinks {
+                previous: NONE,
+                next: head,
+            });
+            if head != NONE {
+                (*self.links(head).as_ptr()).previous = offset;
+            }
+        }
+        control.fl_bitmap.set(control.fl_bitmap.get() | 1 << fl);
+        control.sl_bitmaps[fl].set(control.sl_bitmaps[fl].get() | 1 << sl);
+    }
+
+    /// Removes the block at `offset` from the free lists
+    unsafe fn remove(&self, offset: usize) {
+        let (fl, sl) = mapping(self.size(offset));
+        let control = self.control();
+        unsafe {
+            self.set_free(offset, false);
+            let FreeLinks { previous, next } = self.links(offset).read();
+            if previous == NONE {
+                control.heads[fl][sl].set(next);
+            } else {
+                (*self.links(previous).as_ptr()).next = next;
+            }
+            if next != NONE {
+                (*self.links(next).as_ptr()).previous = previous;
+            }
+        }
+        if control.heads[fl][sl].get() == NONE {
+            control.sl_bitmaps[fl].set(control.sl_bitmaps[fl].get() & !(1 << sl));
+            if control.sl_bitmaps[fl].get() == 0 {
+                control.fl_bitmap.set(control.fl_bitmap.get() & !(1 << fl));
+            }
+        }
+    }
+
+    /// Finds a free block of at least `size` bytes
+    fn find(&self, size: usize) -> Option<usize> {
+        let (mut fl, sl) = mapping_search(size)?;
+        if fl >= FL_COUNT {
+            return None;
+        }
+
+        let control = unsafe { self.control?.as_ref() };
+        let mut sl_bitmap = control.sl_bitmaps[fl].get() & (!0 << sl);
+        if sl_bitmap == 0 {
+            let fl_bitmap = control.fl_bitmap.get() & (!0 << (fl + 1));
+            if fl_bitmap == 0 {
+                return None;
+            }
+            fl = fl_bitmap.trailing_zeros() as usize;
+            sl_bitmap = control.sl_bitmaps[fl].get();
+        }
+        Some(control.heads[fl][sl_bitmap.trailing_zeros() as usize].get())
+    }
+
+    /// Frees the used block at `offset`, merging it with its neighbours
+    unsafe fn free(&self, mut offset: usize) {
+        unsafe {
+            if let Some(next) = self.next_physical(offset)
+                && self.is_free(next)
+            {
+                self.remove(next);
+                self.resize_block(offset, self.size(offset) + self.size(next));
+            }
+
+            let previous = self.read_header(offset).previous_physical;
+            if previous != NONE && self.is_free(previous) {
+                self.remove(previous);
+                self.resize_block(previous, self.size(previous) + self.size(offset));
+                offset = previous;
+            }
+
+            self.insert(offset);
+        }
+    }
+
+    /// Shrinks the used block at `offset` to `size` bytes, if there would be enough left over to make a new block
+    unsafe fn trim(&self, offset: usize, size: usize) {
+        if self.size(offset) - size >= MIN_BLOCK_SIZE {
+            unsafe {
+                let rest = self.split(offset, size);
+                self.free(rest);
+            }
+        }
+    }
+
+    /// Returns the size of the block needed for an allocation of `size` bytes
+    fn block_size(size: usize) -> Option<usize> {
+        Some(
+            align_up(size, GRANULE)?
+                .checked_add(HEADER_SIZE)?
+                .max(MIN_BLOCK_SIZE),
+        )
+    }
+}
+
+unsafe impl Storage for TlsfStorage<'_> {
+    type Handle = TlsfStorageHandle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { self.base.add(handle.offset).cast() }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let size = Self::block_size(layout.size()).ok_or(StorageAllocError)?;
+        let base = self.base.addr().get();
+
+        let mut offset;
+        if layout.align() <= GRANULE {
+            offset = self.find(size).ok_or(StorageAllocError)?;
+            unsafe { self.remove(offset) };
+        } else {
+            // leave enough room to split off a free block before the aligned allocation
+            let search_size = size
+                .checked_add(layout.align())
+                .and_then(|size| size.checked_add(MIN_BLOCK_SIZE))
+                .ok_or(StorageAllocError)?;
+            offset = self.find(search_size).ok_or(StorageAllocError)?;
+
+            let data = base + offset + HEADER_SIZE;
+            let mut aligned = align_up(data, layout.align()).ok_or(StorageAllocError)?;
+            unsafe { self.remove(offset) };
+            if aligned != data {
+                while aligned - data < MIN_BLOCK_SIZE {
+                    aligned += layout.align();
+                }
+                unsafe {
+                    let front = offset;
+                    offset = self.split(front, aligned - data);
+                    self.free(front);
+                }
+            }
+        }
+
+        unsafe { self.trim(offset, size) };
+        Ok((
+            TlsfStorageHandle {
+                offset: offset + HEADER_SIZE,
+            },
+            self.size(offset) - HEADER_SIZE,
+        ))
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        _ = layout;
+        unsafe { self.free(handle.offset - HEADER_SIZE) }
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let offset = handle.offset - HEADER_SIZE;
+        let size = Self::block_size(new_layout.size()).ok_or(StorageAllocError)?;
+
+        if (self.base.addr().get() + handle.offset).is_multiple_of(new_layout.align()) {
+            if self.size(offset) >= size {
+                return Ok((handle, self.size(offset) - HEADER_SIZE));
+            }
+
+            if let Some(next) = self.next_physical(offset)
+                && self.is_free(next)
+                && self.size(offset) + self.size(next) >= size
+            {
+                unsafe {
+                    self.remove(next);
+                    self.resize_block(offset, self.size(offset) + self.size(next));
+                    self.trim(offset, size);
+                }
+                return Ok((handle, self.size(offset) - HEADER_SIZE));
+            }
+        }
+
+        let (new_handle, new_size) = self.allocate(new_layout)?;
+        unsafe {
+            self.resolve(new_handle)
+                .cast::<u8>()
+                .copy_from_nonoverlapping(self.resolve(handle).cast(), old_layout.size());
+            self.free(offset);
+        }
+        Ok((new_handle, new_size))
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        _ = old_layout;
+        let offset = handle.offset - HEADER_SIZE;
+
+        if !(self.base.addr().get() + handle.offset).is_multiple_of(new_layout.align()) {
+            let (new_handle, new_size) = self.allocate(new_layout)?;
+            unsafe {
+                self.resolve(new_handle)
+                    .cast::<u8>()
+                    .copy_from_nonoverlapping(self.resolve(handle).cast(), new_layout.size());
+                self.free(offset);
+            }
+            return Ok((new_handle, new_size));
+        }
+
+        unsafe {
+            let size = Self::block_size(new_layout.size()).unwrap_unchecked();
+            self.trim(offset, size);
+        }
+        Ok((handle, self.size(offset) - HEADER_SIZE))
+    }
+}
+
+unsafe impl MultipleStorage for TlsfStorage<'_> {}
+unsafe impl StableStorage for TlsfStorage<'_> {}
+unsafe impl PointerStorage for TlsfStorage<'_> {
+    unsafe fn handle_from_ptr(&self, ptr: NonNull<()>) -> Self::Handle {
+        TlsfStorageHandle {
+            offset: ptr.addr().get() - self.base.addr().get(),
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use crate::{MultipleStorage, StableStorage, Storage, StorageAllocError};
+use core::{alloc::Layout, cell::Cell, ptr::NonNull};
+
+/// A call to a [`Storage`] recorded by a [`TracingStorage`]
+///
+/// `result` is what the inner [`Storage`] returned, the handle and the size it provided, or the error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceEvent<H> {
+    /// A call to [`Storage::allocate`]
+    Allocate {
+        /// The requested [`Layout`]
+        layout: Layout,
+        /// What the inner [`Storage`] returned
+        result: Result<(H, usize), StorageAllocError>,
+    },
+    /// A call to [`Storage::deallocate`]
+    Deallocate {
+        /// The [`Layout`] of the allocation
+        layout: Layout,
+        /// The handle that was deallocated
+        handle: H,
+    },
+    /// A call to [`Storage::grow`]
+    Grow {
+        /// The [`Layout`] of the allocation before growing
+        old_layout: Layout,
+        /// The requested [`Layout`]
+        new_layout: Layout,
+        /// The handle that was grown
+        handle: H,
+        /// What the inner [`Storage`] returned
+        result: Result<(H, usize), StorageAllocError>,
+    },
+    /// A call to [`Storage::shrink`]
+    Shrink {
+        /// The [`Layout`] of the allocation before shrinking
+        old_layout: Layout,
+        /// The requested [`Layout`]
+        new_layout: Layout,
+        /// The handle that was shrunk
+        handle: H,
+        /// What the inner [`Storage`] returned
+        result: Result<(H, usize), StorageAllocError>,
+    },
+}
+
+/// Receives the [`TraceEvent`]s of a [`TracingStorage`]
+///
+/// This is implemented for closures taking a [`TraceEvent`]
+pub trait TraceSink<H> {
+    /// Called after every call to the inner [`Storage`]
+    fn record(&self, event: TraceEvent<H>);
+}
+
+impl<H, F: Fn(TraceEvent<H>)> TraceSink<H> for F {
+    fn record(&self, event: TraceEvent<H>) {
+        self(event)
+    }
+}
+
+/// A [`TraceSink`] that keeps the last `N` [`TraceEvent`]s, overwriting the oldest ones once it is full
+///
+/// This doesnt allocate, so it can be used without `std`
+pub struct RingBufferSink<H, const N: usize> {
+    events: [Cell<Option<TraceEvent<H>>>; N],
+    start: Cell<usize>,
+    len: Cell<usize>,
+    recorded: Cell<usize>,
+}
+
+impl<H: Copy, const N: usize> RingBufferSink<H, N> {
+    /// Constructs a new empty [`RingBufferSink`]
+    pub const fn new() -> Self {
+        Self {
+            events: [const { Cell::new(None) }; N],
+            start: Cell::new(0),
+            len: Cell::new(0),
+            recorded: Cell::new(0),
+        }
+    }
+
+    /// Returns the number of [`TraceEvent`]s currently kept
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    /// Returns whether there are no [`TraceEvent`]s kept
+    pub fn is_empty(&self) -> bool {
+        self.len.get() == 0
+    }
+
+    /// Returns the total number of [`TraceEvent`]s recorded, including the ones that have been overwritten
+    pub fn recorded(&self) -> usize {
+        self.recorded.get()
+    }
+
+    /// Returns the `index`th oldest [`TraceEvent`] that is still kept
+    pub fn get(&self, index: usize) -> Option<TraceEvent<H>> {
+        if index < self.len.get() {
+            self.events[(self.start.get() + index) % N].get()
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over the [`TraceEvent`]s that are still kept, from oldest to newest
+    pub fn iter(&self) -> impl Iterator<Item = TraceEvent<H>> + '_ {
+        (0..self.len.get()).filter_map(|index| self.get(index))
+    }
+
+    /// Removes all the [`TraceEvent`]s, without resetting [`RingBufferSink::recorded`]
+    pub fn clear(&self) {
+        self.start.set(0);
+        self.len.set(0);
+    }
+}
+
+impl<H: Copy, const N: usize> TraceSink<H> for RingBufferSink<H, N> {
+    fn record(&self, event: TraceEvent<H>) {
+        self.recorded.set(self.recorded.get() + 1);
+        if N == 0 {
+            return;
+        }
+
+        let len = self.len.get();
+        if len < N {
+            self.events[(self.start.get() + len) % N].set(Some(event));
+            self.len.set(len + 1);
+        } else {
+            self.events[self.start.get()].set(Some(event));
+            self.start.set((self.start.get() + 1) % N);
+        }
+    }
+}
+
+impl<H: Copy, const N: usize> Default for RingBufferSink<H, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A wrapper around a [`Storage`] that reports every call to a [`TraceSink`]
+///
+/// ```
+/// use storage_api::{Global, TracingStorage, Vec, storages::{GlobalHandle, RingBufferSink, TraceEvent}};
+/// # use storage_api::StorageAllocError;
+///
+/// # fn main() -> Result<(), StorageAllocError> {
+///
+/// let storage = TracingStorage::new(Global, RingBufferSink::<GlobalHandle, 16>::new());
+/// let mut v = Vec::<u32, _>::with_capacity_in(2, &storage)?;
+/// v.extend_from_slice(&[1, 2, 3])?;
+/// drop(v);
+///
+/// let events = storage.sink();
+/// assert_eq!(events.len(), 3);
+/// assert!(matches!(events.get(0), Some(TraceEvent::Allocate { result: Ok((_, 8)), .. })));
+/// assert!(matches!(events.get(1), Some(TraceEvent::Grow { .. })));
+/// assert!(matches!(events.get(2), Some(TraceEvent::Deallocate { .. })));
+///
+/// # Ok(())
+/// # }
+/// ```
+pub struct TracingStorage<S, Sink> {
+    storage: S,
+    sink: Sink,
+}
+
+impl<S, Sink> TracingStorage<S, Sink> {
+    /// Constructs a new [`TracingStorage`] that reports the calls to `storage` to `sink`
+    pub const fn new(storage: S, sink: Sink) -> Self {
+        Self { storage, sink }
+    }
+
+    /// Returns a reference to the inner [`Storage`]
+    pub fn inner(&self) -> &S {
+        &self.storage
+    }
+
+    /// Returns a reference to the [`TraceSink`]
+    pub fn sink(&self) -> &Sink {
+        &self.sink
+    }
+
+    /// Splits the [`TracingStorage`] into the inner [`Storage`] and the [`TraceSink`]
+    pub fn into_inner(self) -> (S, Sink) {
+        (self.storage, self.sink)
+    }
+}
+
+unsafe impl<S: Storage, Sink: TraceSink<S::Handle>> Storage for TracingStorage<S, Sink> {
+    type Handle = S::Handle;
+
+    unsafe fn resolve(&self, handle: Self::Handle) -> NonNull<()> {
+        unsafe { self.storage.resolve(handle) }
+    }
+
+    fn allocate(&self, layout: Layout) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let result = self.storage.allocate(layout);
+        self.sink.record(TraceEvent::Allocate { layout, result });
+        result
+    }
+
+    unsafe fn deallocate(&self, layout: Layout, handle: Self::Handle) {
+        unsafe { self.storage.deallocate(layout, handle) };
+        self.sink.record(TraceEvent::Deallocate { layout, handle });
+    }
+
+    unsafe fn grow(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let result = unsafe { self.storage.grow(old_layout, new_layout, handle) };
+        self.sink.record(TraceEvent::Grow {
+            old_layout,
+            new_layout,
+            handle,
+            result,
+        });
+        result
+    }
+
+    unsafe fn shrink(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        handle: Self::Handle,
+    ) -> Result<(Self::Handle, usize), StorageAllocError> {
+        let result = unsafe { self.storage.shrink(old_layout, new_layout, handle) };
+        self.sink.record(TraceEvent::Shrink {
+            old_layout,
+            new_layout,
+            handle,
+            result,
+        });
+        result
+    }
+}
+
+unsafe impl<S: MultipleStorage, Sink: TraceSink<S::Handle>> MultipleStorage
+    for TracingStorage<S, Sink>
+{
+}
+unsafe impl<S: StableStorage, Sink: TraceSink<S::Handle>> StableStorage
+    for TracingStorage<S, Sink>
+{
+}
@@ -0,0 +1,45 @@
+//! Tests for zero size allocations in a [`BumpStorage`], which start at the same address as the allocation after them
+
+use std::mem::MaybeUninit;
+use storage_api::{Box, BumpStorage, Vec};
+
+#[test]
+fn zero_size_allocation_before_last() {
+    let mut buffer = [MaybeUninit::uninit(); 64];
+    let storage = BumpStorage::from_uninit(&mut buffer);
+
+    let v = Vec::<u8, _>::new_in(&storage).unwrap();
+    let a = Box::new_in(0xAAu8, &storage).unwrap();
+    // `v` starts where `a` does, but it isnt the last allocation
+    drop(v);
+    let b = Box::new_in(0x55u8, &storage).unwrap();
+    assert_eq!((*a, *b), (0xAA, 0x55));
+
+    let mut v = Vec::<u8, _>::new_in(&storage).unwrap();
+    let c = Box::new_in(0xAAu8, &storage).unwrap();
+    // `v` cant grow in place, because that would overwrite `c`
+    v.push(0x55).unwrap();
+    assert_eq!((*a, *b, *c, &*v), (0xAA, 0x55, 0xAA, &[0x55][..]));
+}
+
+#[test]
+fn zero_size_allocation_after_last() {
+    let mut buffer = [MaybeUninit::uninit(); 64];
+    let storage = BumpStorage::from_uninit(&mut buffer);
+
+    let mut a = Vec::<u8, _>::with_capacity_in(2, &storage).unwrap();
+    a.extend_from_slice(&[1, 2]).unwrap();
+    let v = Vec::<u8, _>::new_in(&storage).unwrap();
+    drop(v);
+    // `a` is still the last allocation, so it grows in place
+    let start = a.as_ptr();
+    a.push(3).unwrap();
+    assert_eq!((&*a, a.as_ptr()), (&[1, 2, 3][..], start));
+
+    let mut v = Vec::<u8, _>::new_in(&storage).unwrap();
+    // `a` grows over `v`, so `v` cant grow in place anymore
+    a.push(4).unwrap();
+    v.extend_from_slice(&[5, 6]).unwrap();
+    let b = Box::new_in(7u8, &storage).unwrap();
+    assert_eq!((&*a, &*v, *b), (&[1, 2, 3, 4][..], &[5, 6][..], 7));
+}
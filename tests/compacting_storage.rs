@@ -0,0 +1,75 @@
+//! Tests for zero size allocations in a [`CompactingStorage`], which can be next to or inside other allocations when it is compacted
+
+use std::{alloc::Layout, mem::MaybeUninit};
+use storage_api::{CompactingStorage, Storage, storages::CompactingStorageHandle};
+
+#[repr(align(64))]
+struct Buffer([MaybeUninit<u8>; 128]);
+
+/// Returns whether `handle` resolves to `layout.size()` bytes inside `storage` that are aligned to `layout.align()`
+fn in_buffer(
+    storage: &CompactingStorage<'_>,
+    start: *const MaybeUninit<u8>,
+    handle: CompactingStorageHandle,
+    layout: Layout,
+) -> bool {
+    let ptr = unsafe { storage.resolve(handle) }.addr().get();
+    let start = start.addr();
+    ptr >= start
+        && ptr + layout.size() <= start + storage.capacity()
+        && ptr.is_multiple_of(layout.align())
+}
+
+#[test]
+fn zero_size_allocation_next_to_other_allocations() {
+    let mut buffer = Buffer([MaybeUninit::uninit(); 128]);
+    let start = buffer.0.as_ptr();
+    let mut storage = CompactingStorage::from_uninit(&mut buffer.0);
+    let byte = Layout::new::<u8>();
+    let zero = Layout::new::<[u64; 0]>();
+
+    let (freed, _) = storage.allocate(Layout::new::<[u8; 16]>()).unwrap();
+    let (a, _) = storage.allocate(byte).unwrap();
+    let (before, _) = storage.allocate(zero).unwrap();
+    let (b, _) = storage.allocate(byte).unwrap();
+    let (after, _) = storage.allocate(zero).unwrap();
+    unsafe {
+        storage.resolve(a).cast::<u8>().write(0xAA);
+        storage.resolve(b).cast::<u8>().write(0x55);
+        storage.deallocate(Layout::new::<[u8; 16]>(), freed);
+    }
+
+    storage.compact();
+    assert_eq!(storage.used(), 2);
+    for (handle, layout) in [(a, byte), (before, zero), (b, byte), (after, zero)] {
+        assert!(in_buffer(&storage, start, handle, layout));
+    }
+    unsafe {
+        assert_eq!(storage.resolve(a).cast::<u8>().read(), 0xAA);
+        assert_eq!(storage.resolve(b).cast::<u8>().read(), 0x55);
+    }
+}
+
+#[test]
+fn zero_size_allocation_inside_another_allocation() {
+    let mut buffer = Buffer([MaybeUninit::uninit(); 128]);
+    let start = buffer.0.as_ptr();
+    // the end of the buffer isnt aligned to 64, so a zero size allocation with that alignment cant be moved past the end of `a`
+    let mut storage = CompactingStorage::from_uninit(&mut buffer.0[..120]);
+    let zero = Layout::from_size_align(0, 64).unwrap();
+
+    let (a, _) = storage.allocate(Layout::new::<[u8; 64]>()).unwrap();
+    let (inside, _) = storage.allocate(zero).unwrap();
+    // `a` is still the last allocation, so it grows in place over `inside`
+    let (a, _) =
+        unsafe { storage.grow(Layout::new::<[u8; 64]>(), Layout::new::<[u8; 120]>(), a) }.unwrap();
+    unsafe { storage.resolve(a).cast::<[u8; 120]>().write([2; 120]) };
+
+    storage.compact();
+    assert!(in_buffer(&storage, start, a, Layout::new::<[u8; 120]>()));
+    assert!(in_buffer(&storage, start, inside, zero));
+    assert_eq!(
+        unsafe { storage.resolve(a).cast::<[u8; 120]>().read() },
+        [2; 120]
+    );
+}
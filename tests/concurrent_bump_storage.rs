@@ -0,0 +1,114 @@
+//! Stress tests for allocating from a [`ConcurrentBumpStorage`] on several threads at once
+
+use std::{alloc::Layout, mem::MaybeUninit, sync::Barrier};
+use storage_api::{Box, ConcurrentBumpStorage, Storage, Vec};
+
+const THREADS: usize = 8;
+
+#[test]
+fn concurrent_allocations_dont_overlap() {
+    let mut storage = ConcurrentBumpStorage::with_capacity(1 << 20).unwrap();
+    let barrier = Barrier::new(THREADS);
+
+    for _ in 0..4 {
+        let ranges = std::thread::scope(|scope| {
+            let threads = (0..THREADS)
+                .map(|thread| {
+                    let (storage, barrier) = (&storage, &barrier);
+                    scope.spawn(move || {
+                        barrier.wait();
+                        let mut ranges = std::vec::Vec::new();
+                        for i in 0..500 {
+                            let layout = Layout::from_size_align(1 + i % 37, 1 << (i % 5)).unwrap();
+                            let (handle, size) = storage.allocate(layout).unwrap();
+                            assert_eq!(size, layout.size());
+                            let ptr = unsafe { storage.resolve(handle) }.cast::<u8>();
+                            assert!(ptr.addr().get().is_multiple_of(layout.align()));
+                            unsafe { ptr.write_bytes(thread as u8, size) };
+                            ranges.push((handle, size, thread as u8));
+                        }
+                        ranges
+                    })
+                })
+                .collect::<std::vec::Vec<_>>();
+            threads
+                .into_iter()
+                .flat_map(|thread| thread.join().unwrap())
+                .collect::<std::vec::Vec<_>>()
+        });
+
+        for &(handle, size, thread) in &ranges {
+            let ptr = unsafe { storage.resolve(handle) }.cast::<u8>();
+            let bytes = unsafe { std::slice::from_raw_parts(ptr.as_ptr(), size) };
+            assert!(bytes.iter().all(|&byte| byte == thread));
+        }
+
+        let mut ranges = ranges
+            .iter()
+            .map(|&(handle, size, _)| (unsafe { storage.resolve(handle) }.addr().get(), size))
+            .collect::<std::vec::Vec<_>>();
+        ranges.sort();
+        for pair in ranges.windows(2) {
+            assert!(pair[0].0 + pair[0].1 <= pair[1].0);
+        }
+        assert!(storage.used() <= storage.capacity());
+
+        storage.reset();
+        assert_eq!(storage.used(), 0);
+    }
+}
+
+#[test]
+fn concurrent_collections_keep_their_contents() {
+    let storage = ConcurrentBumpStorage::with_capacity(1 << 20).unwrap();
+    let barrier = Barrier::new(THREADS);
+
+    std::thread::scope(|scope| {
+        for thread in 0..THREADS {
+            let (storage, barrier) = (&storage, &barrier);
+            scope.spawn(move || {
+                barrier.wait();
+                let mut boxes = std::vec::Vec::new();
+                let mut v = Vec::<usize, _>::new_in(storage).unwrap();
+                for i in 0..1000 {
+                    v.push(thread * 1000 + i).unwrap();
+                    if i % 10 == 0 {
+                        boxes.push(Box::new_in([thread; 4], storage).unwrap());
+                    }
+                }
+                assert!(v.iter().copied().eq(thread * 1000..thread * 1000 + 1000));
+                assert!(boxes.iter().all(|b| **b == [thread; 4]));
+            });
+        }
+    });
+}
+
+#[test]
+fn allocations_fail_once_the_buffer_is_full() {
+    let mut buffer = [MaybeUninit::uninit(); 4096];
+    let storage = ConcurrentBumpStorage::from_uninit(&mut buffer);
+    let layout = Layout::new::<u64>();
+
+    let counts = std::thread::scope(|scope| {
+        let threads = (0..THREADS)
+            .map(|_| {
+                let storage = &storage;
+                scope.spawn(move || {
+                    let mut count = 0;
+                    while storage.allocate(layout).is_ok() {
+                        count += 1;
+                    }
+                    count
+                })
+            })
+            .collect::<std::vec::Vec<_>>();
+        threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect::<std::vec::Vec<usize>>()
+    });
+
+    let total = counts.iter().sum::<usize>() * layout.size();
+    assert!(total <= storage.capacity());
+    assert!(storage.capacity() - total < 2 * layout.align()); // padding at the start and the end
+}
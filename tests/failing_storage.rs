@@ -0,0 +1,173 @@
+//! Drives the collections through allocation failures using [`FailingStorage`]
+
+use std::alloc::Layout;
+use storage_api::{
+    Box, FailingStorage, Global, StorageAllocError, String, Vec,
+    collections::{InsertError, PushError},
+    storages::{FailEveryNth, FailLargerThan, FailNth, FailRandomly, FailurePolicy},
+};
+
+const COUNT: usize = 64;
+
+/// Calls `f` until it succeeds
+fn retry<T>(mut f: impl FnMut() -> Result<T, StorageAllocError>) -> T {
+    loop {
+        if let Ok(value) = f() {
+            return value;
+        }
+    }
+}
+
+/// Pushes `0..COUNT` onto a [`Vec`], retrying every push that fails, and returns how many pushes failed
+fn push_all<P: FailurePolicy>(storage: &FailingStorage<Global, P>) -> usize {
+    let mut v = retry(|| Vec::<usize, _>::new_in(storage));
+
+    let mut failed = 0;
+    for i in 0..COUNT {
+        let mut value = i;
+        loop {
+            match v.push(value) {
+                Ok(pushed) => {
+                    assert_eq!(*pushed, i);
+                    break;
+                }
+                Err(PushError {
+                    value: returned, ..
+                }) => {
+                    assert_eq!(returned, i);
+                    assert_eq!(v.len(), i);
+                    value = returned;
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    assert!(v.iter().copied().eq(0..COUNT));
+    failed
+}
+
+#[test]
+fn vec_push_survives_failure_at_every_step() {
+    let total = FailingStorage::new(Global, FailNth(usize::MAX));
+    push_all(&total);
+    let requests = total.requests();
+
+    for n in 0..requests {
+        let storage = FailingStorage::new(Global, FailNth(n));
+        push_all(&storage);
+        assert_eq!(storage.failures(), 1);
+    }
+}
+
+#[test]
+fn vec_push_survives_every_nth_failure() {
+    for n in 2..8 {
+        let storage = FailingStorage::new(Global, FailEveryNth(n));
+        push_all(&storage);
+        assert!(storage.failures() > 0);
+    }
+}
+
+#[test]
+fn vec_push_survives_random_failures() {
+    for seed in 0..32 {
+        let storage = FailingStorage::new(Global, FailRandomly::new(seed, 3));
+        push_all(&storage);
+    }
+}
+
+#[test]
+fn vec_push_survives_closure_failures() {
+    // fail every third request, starting with the second one
+    let storage = FailingStorage::new(Global, |index: usize, _: Layout| index % 3 == 1);
+    push_all(&storage);
+    assert!(storage.failures() > 0);
+}
+
+#[test]
+fn vec_insert_returns_value_on_failure() {
+    for n in 0..16 {
+        let storage = FailingStorage::new(Global, FailNth(n));
+        let mut v = retry(|| Vec::<std::string::String, _>::new_in(&storage));
+
+        for i in 0..16 {
+            let mut value = i.to_string();
+            loop {
+                match v.insert(0, value) {
+                    Ok(_) => break,
+                    Err(InsertError {
+                        value: returned,
+                        alloc_error,
+                    }) => {
+                        assert!(alloc_error.is_some());
+                        assert_eq!(returned, i.to_string());
+                        value = returned;
+                    }
+                }
+            }
+        }
+
+        assert!(
+            v.iter()
+                .map(|s| s.parse::<i32>().unwrap())
+                .eq((0..16).rev())
+        );
+    }
+}
+
+#[test]
+fn vec_extend_from_slice_keeps_elements_on_failure() {
+    let storage = FailingStorage::new(Global, FailLargerThan(32));
+    let mut v = Vec::<u32, _>::new_in(&storage).unwrap();
+    v.extend_from_slice(&[1, 2, 3, 4]).unwrap();
+    assert!(v.extend_from_slice(&[5, 6, 7, 8, 9]).is_err());
+    assert_eq!(&*v, &[1, 2, 3, 4]);
+    v.extend_from_slice(&[5, 6, 7, 8]).unwrap();
+    assert_eq!(&*v, &[1, 2, 3, 4, 5, 6, 7, 8]);
+}
+
+#[test]
+fn string_push_str_survives_failure_at_every_step() {
+    let words = [
+        "the", "quick", "brown", "fox", "jumps", "over", "the", "lazy", "dog",
+    ];
+    let expected = words.concat();
+
+    for n in 0..32 {
+        let storage = FailingStorage::new(Global, FailNth(n));
+        let mut s = retry(|| String::new_in(&storage));
+
+        for word in words {
+            let length = s.len();
+            while s.push_str(word).is_err() {
+                assert_eq!(s.len(), length);
+            }
+        }
+
+        assert_eq!(&*s, expected);
+    }
+}
+
+#[test]
+fn string_push_survives_random_failures() {
+    for seed in 1..16 {
+        let storage = FailingStorage::new(Global, FailRandomly::new(seed, 2));
+        let mut s = retry(|| String::new_in(&storage));
+
+        for c in "hello, world".chars() {
+            while s.push(c).is_err() {}
+        }
+
+        assert_eq!(&*s, "hello, world");
+    }
+}
+
+#[test]
+fn box_new_in_fails_cleanly() {
+    let storage = FailingStorage::new(Global, FailNth(0));
+    assert!(Box::new_in([1u64; 4], &storage).is_err());
+    let b = Box::new_in([1u64; 4], &storage).unwrap();
+    assert_eq!(*b, [1; 4]);
+    assert_eq!((storage.requests(), storage.failures()), (2, 1));
+}
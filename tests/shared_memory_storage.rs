@@ -0,0 +1,200 @@
+//! Tests for sending collections between processes with a [`SharedMemoryStorage`]
+//!
+//! These dont use the libtest harness, because it runs tests on several threads and forking a process with several threads can deadlock the child
+
+fn main() {
+    #[cfg(all(feature = "unix", target_os = "linux"))]
+    for (name, test) in [
+        (
+            "child_reads_string_from_parent",
+            linux::child_reads_string_from_parent as fn(),
+        ),
+        (
+            "both_processes_allocate_at_the_same_time",
+            linux::both_processes_allocate_at_the_same_time,
+        ),
+        (
+            "from_fd_rejects_other_memory",
+            linux::from_fd_rejects_other_memory,
+        ),
+    ] {
+        print!("test {name} ... ");
+        test();
+        println!("ok");
+    }
+}
+
+#[cfg(all(feature = "unix", target_os = "linux"))]
+mod linux {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use storage_api::{SharedMemoryStorage, String, Vec, storages::SharedMemoryStorageHandle};
+
+    /// The parts of a collection that are sent to the other process
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    #[repr(C)]
+    struct RawParts {
+        handle: SharedMemoryStorageHandle,
+        length: usize,
+        capacity: usize,
+    }
+
+    fn pipe() -> (OwnedFd, OwnedFd) {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) }
+    }
+
+    fn send(fd: &OwnedFd, parts: RawParts) {
+        let written = unsafe {
+            libc::write(
+                fd.as_raw_fd(),
+                (&raw const parts).cast(),
+                size_of::<RawParts>(),
+            )
+        };
+        assert_eq!(written, size_of::<RawParts>() as isize);
+    }
+
+    fn receive(fd: &OwnedFd) -> RawParts {
+        let mut parts = core::mem::MaybeUninit::<RawParts>::uninit();
+        let read = unsafe {
+            libc::read(
+                fd.as_raw_fd(),
+                parts.as_mut_ptr().cast(),
+                size_of::<RawParts>(),
+            )
+        };
+        assert_eq!(read, size_of::<RawParts>() as isize);
+        unsafe { parts.assume_init() }
+    }
+
+    /// Forks and runs `child` in the child process, returns the id of the child
+    fn spawn(child: impl FnOnce() -> bool) -> libc::pid_t {
+        match unsafe { libc::fork() } {
+            -1 => panic!("fork failed: {}", std::io::Error::last_os_error()),
+            0 => {
+                // dont unwind into the test harness in the child
+                let success = std::panic::catch_unwind(std::panic::AssertUnwindSafe(child));
+                unsafe { libc::_exit(if matches!(success, Ok(true)) { 0 } else { 1 }) }
+            }
+            pid => pid,
+        }
+    }
+
+    /// Waits for the child process to exit and returns whether it succeeded
+    fn wait(pid: libc::pid_t) -> bool {
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0
+    }
+
+    pub fn child_reads_string_from_parent() {
+        let storage = SharedMemoryStorage::new(1 << 16).unwrap();
+        let fd = storage.try_clone_fd().unwrap();
+        let (to_child, from_parent) = (pipe(), pipe());
+
+        let s = String::from_str_in("hello from the parent", &storage).unwrap();
+        let (_, handle, length, capacity) = s.into_raw_parts();
+        send(
+            &to_child.1,
+            RawParts {
+                handle,
+                length,
+                capacity,
+            },
+        );
+
+        let child = spawn(|| {
+            // map the memory again so it is at a different address than in the parent
+            let storage = unsafe { SharedMemoryStorage::from_fd(fd) }.unwrap();
+            let RawParts {
+                handle,
+                length,
+                capacity,
+            } = receive(&to_child.0);
+            let mut s = unsafe { String::from_raw_parts(&storage, handle, length, capacity) };
+            if &*s != "hello from the parent" {
+                return false;
+            }
+
+            s.push_str(", and hello from the child").unwrap();
+            let (_, handle, length, capacity) = s.into_raw_parts();
+            send(
+                &from_parent.1,
+                RawParts {
+                    handle,
+                    length,
+                    capacity,
+                },
+            );
+            true
+        });
+        assert!(wait(child));
+
+        let RawParts {
+            handle,
+            length,
+            capacity,
+        } = receive(&from_parent.0);
+        let s = unsafe { String::from_raw_parts(&storage, handle, length, capacity) };
+        assert_eq!(&*s, "hello from the parent, and hello from the child");
+    }
+
+    pub fn both_processes_allocate_at_the_same_time() {
+        const COUNT: usize = 1000;
+
+        let storage = SharedMemoryStorage::new(1 << 20).unwrap();
+        let fd = storage.try_clone_fd().unwrap();
+        let to_parent = pipe();
+
+        let child = spawn(|| {
+            let storage = unsafe { SharedMemoryStorage::from_fd(fd) }.unwrap();
+            let mut v = Vec::<usize, _>::new_in(&storage).unwrap();
+            for i in 0..COUNT {
+                v.push(i * 2).unwrap();
+            }
+            let (_, handle, length, capacity) = v.into_raw_parts();
+            send(
+                &to_parent.1,
+                RawParts {
+                    handle,
+                    length,
+                    capacity,
+                },
+            );
+            true
+        });
+
+        let mut v = Vec::<usize, _>::new_in(&storage).unwrap();
+        for i in 0..COUNT {
+            v.push(i * 3).unwrap();
+        }
+        assert!(wait(child));
+
+        let RawParts {
+            handle,
+            length,
+            capacity,
+        } = receive(&to_parent.0);
+        let child = unsafe { Vec::<usize, _>::from_raw_parts(&storage, handle, length, capacity) };
+        assert!(child.iter().copied().eq((0..COUNT).map(|i| i * 2)));
+        assert!(v.iter().copied().eq((0..COUNT).map(|i| i * 3)));
+
+        let (child, parent) = (child.into_raw_parts(), v.into_raw_parts());
+        let (child_start, parent_start) = (child.1.offset, parent.1.offset);
+        let (child_end, parent_end) = (
+            child_start + child.3 * size_of::<usize>(),
+            parent_start + parent.3 * size_of::<usize>(),
+        );
+        assert!(child_end <= parent_start || parent_end <= child_start);
+    }
+
+    pub fn from_fd_rejects_other_memory() {
+        let fd = unsafe { libc::memfd_create(c"not_storage".as_ptr(), libc::MFD_CLOEXEC) };
+        assert!(fd >= 0);
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        assert_eq!(unsafe { libc::ftruncate(fd.as_raw_fd(), 4096) }, 0);
+
+        assert!(unsafe { SharedMemoryStorage::from_fd(fd) }.is_err());
+    }
+}